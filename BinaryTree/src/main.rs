@@ -1,4 +1,4 @@
-use std::cmp::Ord;
+use std::cmp::{Ord, Ordering};
 
 enum BinaryTree<T> {
     Empty,
@@ -32,6 +32,66 @@ impl<T: Ord> BinaryTree<T> {
             }
         }
     }
+
+    fn contains(&self, value: &T) -> bool {
+        match *self {
+            Empty => false,
+            NonEmpty(ref node) => match value.cmp(&node.element) {
+                Ordering::Less => node.left.contains(value),
+                Ordering::Greater => node.right.contains(value),
+                Ordering::Equal => true,
+            },
+        }
+    }
+
+    fn remove(&mut self, value: &T) -> Option<T> {
+        match *self {
+            Empty => None,
+            NonEmpty(ref mut node) => match value.cmp(&node.element) {
+                Ordering::Less => node.left.remove(value),
+                Ordering::Greater => node.right.remove(value),
+                Ordering::Equal => Some(self.remove_root()),
+            },
+        }
+    }
+
+    // Removes the smallest element, which is the leftmost node.
+    fn remove_min(&mut self) -> Option<T> {
+        match *self {
+            Empty => None,
+            NonEmpty(ref mut node) => match node.left {
+                Empty => Some(self.remove_root()),
+                NonEmpty(_) => node.left.remove_min(),
+            },
+        }
+    }
+
+    // Unlinks the root node of a non-empty tree and returns its element. A
+    // node with two children is replaced by its in-order successor, the
+    // smallest element of the right subtree.
+    fn remove_root(&mut self) -> T {
+        let node = match std::mem::replace(self, Empty) {
+            Empty => panic!("remove_root called on an empty tree"),
+            NonEmpty(node) => node,
+        };
+        let TreeNode {
+            element,
+            left,
+            right,
+        } = *node;
+        *self = match (left, right) {
+            (child, Empty) | (Empty, child) => child,
+            (left, mut right) => {
+                let successor = right.remove_min().expect("right subtree is not empty");
+                NonEmpty(Box::new(TreeNode {
+                    element: successor,
+                    left,
+                    right,
+                }))
+            }
+        };
+        element
+    }
 }
 
 struct TreeIter<'a, T: 'a> {
@@ -48,7 +108,7 @@ impl<'a, T: 'a> TreeIter<'a, T> {
 }
 
 impl<T> BinaryTree<T> {
-    fn iter(&self) -> TreeIter<T> {
+    fn iter(&self) -> TreeIter<'_, T> {
         let mut iter = TreeIter {
             unvisited: Vec::new(),
         };
@@ -68,10 +128,7 @@ impl<'a, T: 'a> IntoIterator for &'a BinaryTree<T> {
 impl<'a, T: 'a> Iterator for TreeIter<'a, T> {
     type Item = &'a T;
     fn next(&mut self) -> Option<Self::Item> {
-        let node = match self.unvisited.pop() {
-            None => return None,
-            Some(n) => n,
-        };
+        let node = self.unvisited.pop()?;
         self.push_left_edge(&node.right);
         Some(&node.element)
     }
@@ -95,4 +152,109 @@ fn main() {
         .map(|planet| format!("Hello, {}", planet))
         .collect::<Vec<_>>();
     println!("{:?}", greetings);
+
+    let removed = tree.remove(&String::from("Mercury"));
+    println!(
+        "Removed {:?}, contains Mercury: {}",
+        removed,
+        tree.contains(&String::from("Mercury"))
+    );
+    println!("{:?}", tree.iter().collect::<Vec<_>>());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree_of(values: &[i32]) -> BinaryTree<i32> {
+        let mut tree = Empty;
+        for &value in values {
+            tree.add(value);
+        }
+        tree
+    }
+
+    fn elements(tree: &BinaryTree<i32>) -> Vec<i32> {
+        tree.iter().copied().collect()
+    }
+
+    //         50
+    //       /    \
+    //     30      70
+    //    /  \    /  \
+    //  20   40  60   80
+    //             \
+    //             65
+    fn sample() -> BinaryTree<i32> {
+        tree_of(&[50, 30, 70, 20, 40, 60, 80, 65])
+    }
+
+    #[test]
+    fn contains_finds_only_added_values() {
+        let tree = sample();
+        assert!(tree.contains(&50));
+        assert!(tree.contains(&65));
+        assert!(!tree.contains(&55));
+        assert!(!Empty.contains(&1));
+    }
+
+    #[test]
+    fn remove_missing_value_leaves_tree_alone() {
+        let mut tree = sample();
+        assert_eq!(tree.remove(&55), None);
+        assert_eq!(elements(&tree), vec![20, 30, 40, 50, 60, 65, 70, 80]);
+        assert_eq!(Empty.remove(&1), None);
+    }
+
+    #[test]
+    fn remove_leaf() {
+        let mut tree = sample();
+        assert_eq!(tree.remove(&20), Some(20));
+        assert!(!tree.contains(&20));
+        assert_eq!(elements(&tree), vec![30, 40, 50, 60, 65, 70, 80]);
+    }
+
+    #[test]
+    fn remove_node_with_one_child() {
+        let mut tree = sample();
+        assert_eq!(tree.remove(&60), Some(60));
+        assert_eq!(elements(&tree), vec![20, 30, 40, 50, 65, 70, 80]);
+        assert!(tree.contains(&65));
+    }
+
+    #[test]
+    fn remove_node_with_two_children_uses_successor() {
+        let mut tree = sample();
+        assert_eq!(tree.remove(&70), Some(70));
+        assert_eq!(elements(&tree), vec![20, 30, 40, 50, 60, 65, 80]);
+        match tree {
+            NonEmpty(ref root) => match root.right {
+                NonEmpty(ref node) => assert_eq!(node.element, 80),
+                Empty => panic!("right subtree should not be empty"),
+            },
+            Empty => panic!("tree should not be empty"),
+        }
+    }
+
+    #[test]
+    fn remove_root_until_empty() {
+        let mut tree = sample();
+        assert_eq!(tree.remove(&50), Some(50));
+        assert_eq!(elements(&tree), vec![20, 30, 40, 60, 65, 70, 80]);
+        for value in [20, 30, 40, 60, 65, 70, 80] {
+            assert_eq!(tree.remove(&value), Some(value));
+        }
+        assert!(matches!(tree, Empty));
+    }
+
+    #[test]
+    fn remove_duplicates_one_at_a_time() {
+        let mut tree = tree_of(&[5, 3, 5, 8, 5]);
+        assert_eq!(tree.remove(&5), Some(5));
+        assert_eq!(elements(&tree), vec![3, 5, 5, 8]);
+        assert_eq!(tree.remove(&5), Some(5));
+        assert_eq!(tree.remove(&5), Some(5));
+        assert_eq!(tree.remove(&5), None);
+        assert_eq!(elements(&tree), vec![3, 8]);
+    }
 }
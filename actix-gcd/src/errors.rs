@@ -0,0 +1,79 @@
+// Turns bare error responses (unknown routes, extractor failures, panics in
+// handlers) into the same formats the handlers use: a styled page for the
+// HTML side and `{ "error": .. }` for the JSON API.
+
+use actix_web::{
+    body::MessageBody,
+    dev::ServiceResponse,
+    http::{header, StatusCode},
+    middleware::ErrorHandlerResponse,
+    HttpResponse,
+};
+
+use crate::templates::{render, ErrorTemplate};
+use crate::{prefers_json, ErrorResponse};
+
+// Responses the handlers already formatted themselves are left alone.
+fn is_formatted<B>(res: &ServiceResponse<B>) -> bool {
+    res.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value.starts_with("application/json") || value.starts_with("text/html")
+        })
+}
+
+fn describe<B>(res: &ServiceResponse<B>) -> (&'static str, String) {
+    let status = res.status();
+    match status {
+        StatusCode::NOT_FOUND => (
+            "Page Not Found",
+            "The page you are looking for does not exist.".to_string(),
+        ),
+        // Internal details stay in the log, not in the page.
+        _ if status.is_server_error() => (
+            "Server Error",
+            "Something went wrong on our side. Please try again later.".to_string(),
+        ),
+        _ => {
+            let reason = status.canonical_reason().unwrap_or("Error");
+            let message = match res.response().error() {
+                Some(err) => err.to_string(),
+                None => reason.to_string(),
+            };
+            (reason, message)
+        }
+    }
+}
+
+pub fn render_error<B: MessageBody + 'static>(
+    res: ServiceResponse<B>,
+) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    if is_formatted(&res) {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+    }
+
+    let status = res.status();
+    let (title, message) = describe(&res);
+    let retry_after = res.headers().get(header::RETRY_AFTER).cloned();
+    let (req, _) = res.into_parts();
+    let mut response = if req.path().starts_with("/api/") || prefers_json(&req) {
+        HttpResponse::build(status).json(ErrorResponse { error: message })
+    } else {
+        render(
+            status,
+            &ErrorTemplate {
+                title,
+                message: &message,
+            },
+        )
+    };
+    if let Some(retry_after) = retry_after {
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, retry_after);
+    }
+    Ok(ErrorHandlerResponse::Response(
+        ServiceResponse::new(req, response).map_into_right_body(),
+    ))
+}
@@ -0,0 +1,76 @@
+// Recent GCD calculations, kept in memory and shared by every worker.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+// How many calculations are kept before the oldest are dropped.
+pub const CAPACITY: usize = 100;
+
+#[derive(Clone, Serialize)]
+pub struct CalcRecord {
+    pub timestamp: DateTime<Utc>,
+    pub numbers: Vec<u64>,
+    pub gcd: u64,
+}
+
+pub struct History {
+    records: Mutex<VecDeque<CalcRecord>>,
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        History {
+            records: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    pub fn record(&self, numbers: Vec<u64>, gcd: u64) {
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if records.len() == self.capacity {
+            records.pop_back();
+        }
+        records.push_front(CalcRecord {
+            timestamp: Utc::now(),
+            numbers,
+            gcd,
+        });
+    }
+
+    /// The `limit` most recent calculations, newest first.
+    pub fn recent(&self, limit: usize) -> Vec<CalcRecord> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records.iter().take(limit).cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.records
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_newest_records_up_to_capacity() {
+        let history = History::new(2);
+        history.record(vec![4, 6], 2);
+        history.record(vec![9, 12], 3);
+        history.record(vec![10, 15], 5);
+
+        let gcds: Vec<u64> = history.recent(10).iter().map(|r| r.gcd).collect();
+        assert_eq!(gcds, vec![5, 3]);
+        assert_eq!(history.recent(1).len(), 1);
+
+        history.clear();
+        assert!(history.recent(10).is_empty());
+    }
+}
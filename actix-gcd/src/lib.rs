@@ -0,0 +1,527 @@
+use actix_files as fs;
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    error,
+    http::{
+        header::{self, Header},
+        StatusCode,
+    },
+    middleware::{from_fn, ErrorHandlers, Logger},
+    web, App, HttpRequest, HttpResponse, Resource,
+};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::fmt;
+
+mod errors;
+pub mod history;
+pub mod limits;
+mod metrics;
+mod numtheory;
+mod templates;
+mod ws;
+
+use history::History;
+use limits::RateLimiter;
+use templates::{
+    render, Calculation, ErrorTemplate, HistoryTemplate, IndexTemplate, ResultTemplate,
+};
+
+// A list of numbers, written as `12,18,24` in forms and query strings or as
+// a plain array in JSON bodies.
+struct NumberList(Vec<u64>);
+
+impl<'de> Deserialize<'de> for NumberList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NumberListVisitor;
+
+        impl<'de> de::Visitor<'de> for NumberListVisitor {
+            type Value = NumberList;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a comma-separated list of numbers")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<NumberList, E> {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(|s| {
+                        s.parse()
+                            .map_err(|_| E::custom(format!("invalid number `{}`", s)))
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(NumberList)
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<NumberList, A::Error> {
+                let mut numbers = Vec::new();
+                while let Some(n) = seq.next_element()? {
+                    numbers.push(n);
+                }
+                Ok(NumberList(numbers))
+            }
+        }
+
+        deserializer.deserialize_any(NumberListVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct GcdParameters {
+    numbers: Option<NumberList>,
+    // The original two-number form, still accepted when `numbers` is absent.
+    a: Option<u64>,
+    b: Option<u64>,
+    #[serde(default)]
+    show_steps: bool,
+}
+
+// Upper bound on how many numbers a single GCD or LCM may take.
+const MAX_NUMBERS: usize = 100;
+
+impl GcdParameters {
+    fn numbers(&self, operation: &str) -> Result<Vec<u64>, String> {
+        let numbers: Vec<u64> = match self.numbers {
+            Some(ref list) => list.0.clone(),
+            None => self.a.into_iter().chain(self.b).collect(),
+        };
+        if numbers.len() < 2 {
+            return Err(format!(
+                "At least two numbers are needed to compute the {}",
+                operation
+            ));
+        }
+        if numbers.len() > MAX_NUMBERS {
+            return Err(format!(
+                "At most {} numbers can be used to compute the {}",
+                MAX_NUMBERS, operation
+            ));
+        }
+        if numbers.contains(&0) {
+            return Err(format!("Cannot compute {} for zero values", operation));
+        }
+        Ok(numbers)
+    }
+
+    // Echoed back as `a` and `b` for requests in the two-number form, so
+    // clients written against that form keep working.
+    fn pair(&self) -> (Option<u64>, Option<u64>) {
+        match self.numbers {
+            Some(_) => (None, None),
+            None => (self.a, self.b),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FactorizeParameters {
+    n: u64,
+}
+
+#[derive(Deserialize)]
+struct ModInvParameters {
+    a: u64,
+    m: u64,
+}
+
+#[derive(Serialize)]
+struct GcdResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    a: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    b: Option<u64>,
+    numbers: Vec<u64>,
+    gcd: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    steps: Option<Vec<numtheory::EuclidStep>>,
+    // Only defined for a pair of numbers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bezout: Option<Bezout>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    continued_fraction: Option<Vec<u64>>,
+}
+
+/// Coefficients with `a*x + b*y = gcd(a, b)`.
+#[derive(Serialize)]
+struct Bezout {
+    x: i128,
+    y: i128,
+}
+
+#[derive(Serialize)]
+struct LcmResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    a: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    b: Option<u64>,
+    numbers: Vec<u64>,
+    lcm: u64,
+}
+
+#[derive(Serialize)]
+struct Factor {
+    prime: u64,
+    exponent: u32,
+}
+
+#[derive(Serialize)]
+struct FactorizeResponse {
+    n: u64,
+    factors: Vec<Factor>,
+}
+
+#[derive(Serialize)]
+struct ModInvResponse {
+    a: u64,
+    m: u64,
+    inverse: u64,
+}
+
+#[derive(Deserialize)]
+struct HistoryParameters {
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+fn gcd_result(params: &GcdParameters) -> Result<GcdResponse, String> {
+    let numbers = params.numbers("GCD")?;
+    metrics::record_computation("gcd");
+    // With more than two numbers the GCD is folded pairwise, so the steps of
+    // each pair follow one another.
+    let mut steps = Vec::new();
+    let gcd = numbers[1..].iter().fold(numbers[0], |acc, &n| {
+        let (gcd, pair_steps) = numtheory::gcd_with_steps(acc, n);
+        steps.extend(pair_steps);
+        gcd
+    });
+    let (bezout, continued_fraction) = match numbers[..] {
+        [a, b] => {
+            let (_, x, y) = numtheory::extended_gcd(a, b);
+            (
+                Some(Bezout { x, y }),
+                Some(numtheory::continued_fraction(a, b)),
+            )
+        }
+        _ => (None, None),
+    };
+    let (a, b) = params.pair();
+    Ok(GcdResponse {
+        a,
+        b,
+        numbers,
+        gcd,
+        steps: params.show_steps.then_some(steps),
+        bezout,
+        continued_fraction,
+    })
+}
+
+// Computes the GCD and adds it to the history when it succeeds.
+fn recorded_gcd_result(params: &GcdParameters, history: &History) -> Result<GcdResponse, String> {
+    let result = gcd_result(params)?;
+    history.record(result.numbers.clone(), result.gcd);
+    Ok(result)
+}
+
+fn lcm_result(params: &GcdParameters) -> Result<LcmResponse, String> {
+    let numbers = params.numbers("LCM")?;
+    let lcm = numbers
+        .iter()
+        .try_fold(1, |acc, &n| numtheory::lcm(acc, n))
+        .ok_or_else(|| {
+            format!(
+                "The LCM of {} does not fit in 64 bits",
+                list_numbers(&numbers)
+            )
+        })?;
+    metrics::record_computation("lcm");
+    let (a, b) = params.pair();
+    Ok(LcmResponse { a, b, numbers, lcm })
+}
+
+fn factorize_result(params: &FactorizeParameters) -> Result<FactorizeResponse, String> {
+    if params.n < 2 {
+        return Err("Only numbers greater than 1 can be factorized".to_string());
+    }
+    metrics::record_computation("factorize");
+    let factors = numtheory::factorize(params.n)
+        .into_iter()
+        .map(|(prime, exponent)| Factor { prime, exponent })
+        .collect();
+    Ok(FactorizeResponse {
+        n: params.n,
+        factors,
+    })
+}
+
+fn modinv_result(params: &ModInvParameters) -> Result<ModInvResponse, String> {
+    if params.m < 2 {
+        return Err("The modulus must be at least 2".to_string());
+    }
+    let inverse = numtheory::mod_inverse(params.a, params.m).ok_or_else(|| {
+        format!(
+            "{} has no inverse modulo {} because they are not coprime",
+            params.a, params.m
+        )
+    })?;
+    metrics::record_computation("modinv");
+    Ok(ModInvResponse {
+        a: params.a,
+        m: params.m,
+        inverse,
+    })
+}
+
+// Joins numbers for display, e.g. "12, 18 and 24".
+fn list_numbers(numbers: &[u64]) -> String {
+    let strings: Vec<String> = numbers.iter().map(u64::to_string).collect();
+    match strings.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        _ => strings.concat(),
+    }
+}
+
+// Writes a continued fraction as "[q0; q1, q2, ...]".
+fn format_continued_fraction(terms: &[u64]) -> String {
+    match terms.split_first() {
+        Some((first, rest)) => {
+            let rest: Vec<String> = rest.iter().map(u64::to_string).collect();
+            format!("[{}; {}]", first, rest.join(", "))
+        }
+        None => "[]".to_string(),
+    }
+}
+
+fn html_result(result: Result<Calculation, String>) -> HttpResponse {
+    match result {
+        Ok(calculation) => render(StatusCode::OK, &ResultTemplate { calculation }),
+        Err(message) => render(
+            StatusCode::BAD_REQUEST,
+            &ErrorTemplate {
+                title: "Error",
+                message: &format!("{}. Please go back and enter valid numbers.", message),
+            },
+        ),
+    }
+}
+
+// Whether the client ranks JSON above everything else it accepts.
+fn prefers_json(req: &HttpRequest) -> bool {
+    header::Accept::parse(req)
+        .ok()
+        .and_then(|accept| accept.ranked().into_iter().next())
+        .is_some_and(|mime| mime.essence_str() == "application/json")
+}
+
+// Form posts answer with a page, unless the client asked for JSON.
+fn negotiate(req: &HttpRequest, result: Result<Calculation, String>) -> HttpResponse {
+    if prefers_json(req) {
+        json_result(result)
+    } else {
+        html_result(result)
+    }
+}
+
+fn json_error(message: impl Into<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(ErrorResponse {
+        error: message.into(),
+    })
+}
+
+fn json_result<T: Serialize>(result: Result<T, String>) -> HttpResponse {
+    match result {
+        Ok(value) => HttpResponse::Ok().json(value),
+        Err(message) => json_error(message),
+    }
+}
+
+// Shared by the query string and JSON body extractors so a malformed or
+// missing number comes back as a JSON 400 instead of actix's plain text.
+fn api_payload_error(err: impl Into<error::Error>, _req: &HttpRequest) -> error::Error {
+    let err = err.into();
+    let response = json_error(err.to_string());
+    error::InternalError::from_response(err, response).into()
+}
+
+async fn get_index() -> HttpResponse {
+    render(StatusCode::OK, &IndexTemplate)
+}
+
+async fn post_gcd(
+    req: HttpRequest,
+    form: web::Form<GcdParameters>,
+    history: web::Data<History>,
+) -> HttpResponse {
+    negotiate(
+        &req,
+        recorded_gcd_result(&form, &history).map(Calculation::Gcd),
+    )
+}
+
+async fn post_lcm(req: HttpRequest, form: web::Form<GcdParameters>) -> HttpResponse {
+    negotiate(&req, lcm_result(&form).map(Calculation::Lcm))
+}
+
+async fn post_factorize(req: HttpRequest, form: web::Form<FactorizeParameters>) -> HttpResponse {
+    negotiate(&req, factorize_result(&form).map(Calculation::Factorize))
+}
+
+async fn post_modinv(req: HttpRequest, form: web::Form<ModInvParameters>) -> HttpResponse {
+    negotiate(&req, modinv_result(&form).map(Calculation::ModInv))
+}
+
+async fn get_api_gcd(
+    query: web::Query<GcdParameters>,
+    history: web::Data<History>,
+) -> HttpResponse {
+    json_result(recorded_gcd_result(&query, &history))
+}
+
+async fn post_api_gcd(body: web::Json<GcdParameters>, history: web::Data<History>) -> HttpResponse {
+    json_result(recorded_gcd_result(&body, &history))
+}
+
+const DEFAULT_HISTORY_LIMIT: usize = 20;
+
+async fn get_history(
+    query: web::Query<HistoryParameters>,
+    history: web::Data<History>,
+) -> HttpResponse {
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+    let records = history.recent(limit);
+    render(StatusCode::OK, &HistoryTemplate { records })
+}
+
+async fn post_clear_history(history: web::Data<History>) -> HttpResponse {
+    history.clear();
+    HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, "/history"))
+        .finish()
+}
+
+async fn get_api_history(
+    query: web::Query<HistoryParameters>,
+    history: web::Data<History>,
+) -> HttpResponse {
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+    HttpResponse::Ok().json(history.recent(limit))
+}
+
+async fn delete_api_history(history: web::Data<History>) -> HttpResponse {
+    history.clear();
+    HttpResponse::NoContent().finish()
+}
+
+async fn get_api_lcm(query: web::Query<GcdParameters>) -> HttpResponse {
+    json_result(lcm_result(&query))
+}
+
+async fn post_api_lcm(body: web::Json<GcdParameters>) -> HttpResponse {
+    json_result(lcm_result(&body))
+}
+
+async fn get_api_factorize(query: web::Query<FactorizeParameters>) -> HttpResponse {
+    json_result(factorize_result(&query))
+}
+
+async fn post_api_factorize(body: web::Json<FactorizeParameters>) -> HttpResponse {
+    json_result(factorize_result(&body))
+}
+
+async fn get_api_modinv(query: web::Query<ModInvParameters>) -> HttpResponse {
+    json_result(modinv_result(&query))
+}
+
+async fn post_api_modinv(body: web::Json<ModInvParameters>) -> HttpResponse {
+    json_result(modinv_result(&body))
+}
+
+// Only the calculations cost anything to serve, so they alone count against
+// the rate limit; the WebSocket counts each message itself.
+fn limited(
+    path: &str,
+) -> Resource<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    web::resource(path).wrap(from_fn(limits::limit_requests))
+}
+
+/// Builds the application with all routes and middleware. The shared state
+/// is passed in so every server worker, or test, sees the same history and
+/// rate limits.
+pub fn app(
+    history: web::Data<History>,
+    limiter: web::Data<RateLimiter>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    App::new()
+        .app_data(history)
+        .app_data(limiter)
+        .app_data(web::FormConfig::default().limit(limits::MAX_FORM_BYTES))
+        .wrap(ErrorHandlers::new().default_handler(errors::render_error))
+        .wrap(Logger::default())
+        .wrap(from_fn(metrics::track_requests))
+        // Serve static files from the "static" folder
+        .service(fs::Files::new("/static", "./static").show_files_listing())
+        // Serve the GCD form
+        .service(limited("/gcd").route(web::post().to(post_gcd)))
+        .service(limited("/lcm").route(web::post().to(post_lcm)))
+        .service(limited("/factorize").route(web::post().to(post_factorize)))
+        .service(limited("/modinv").route(web::post().to(post_modinv)))
+        .route("/history", web::get().to(get_history))
+        .route("/history/clear", web::post().to(post_clear_history))
+        // Liveness and Prometheus endpoints
+        .route("/healthz", web::get().to(metrics::get_healthz))
+        .route("/metrics", web::get().to(metrics::get_metrics))
+        // Live calculator over a WebSocket
+        .route("/ws", web::get().to(ws::ws))
+        // JSON API for programmatic use
+        .service(
+            web::scope("/api")
+                .app_data(web::QueryConfig::default().error_handler(api_payload_error))
+                .app_data(web::JsonConfig::default().error_handler(api_payload_error))
+                .service(
+                    limited("/gcd")
+                        .route(web::get().to(get_api_gcd))
+                        .route(web::post().to(post_api_gcd)),
+                )
+                .service(
+                    limited("/lcm")
+                        .route(web::get().to(get_api_lcm))
+                        .route(web::post().to(post_api_lcm)),
+                )
+                .service(
+                    limited("/factorize")
+                        .route(web::get().to(get_api_factorize))
+                        .route(web::post().to(post_api_factorize)),
+                )
+                .service(
+                    limited("/modinv")
+                        .route(web::get().to(get_api_modinv))
+                        .route(web::post().to(post_api_modinv)),
+                )
+                .route("/history", web::get().to(get_api_history))
+                .route("/history", web::delete().to(delete_api_history)),
+        )
+        // Render the calculator forms as the main page
+        .route("/", web::get().to(get_index))
+}
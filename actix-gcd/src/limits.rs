@@ -0,0 +1,132 @@
+// Per-client rate limiting and the request size caps that keep the public
+// calculator cheap to run.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    error,
+    http::header::{self, HeaderValue},
+    middleware::Next,
+    web, Error,
+};
+
+// Largest urlencoded form body accepted, in bytes.
+pub const MAX_FORM_BYTES: usize = 4 * 1024;
+
+pub const REQUESTS_PER_WINDOW: u32 = 60;
+pub const WINDOW: Duration = Duration::from_secs(60);
+
+// Idle clients are swept out once every this many checks rather than on
+// each one, so a busy limiter doesn't walk the whole map per request.
+const PRUNE_EVERY: u32 = 1024;
+
+#[derive(Default)]
+struct Clients {
+    windows: HashMap<IpAddr, (Instant, u32)>,
+    checks_since_prune: u32,
+}
+
+/// Fixed-window request counter keyed by client IP.
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    clients: Mutex<Clients>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        RateLimiter {
+            limit,
+            window,
+            clients: Mutex::new(Clients::default()),
+        }
+    }
+
+    /// Counts a request from `ip`, or returns how long it has to wait when
+    /// it is over the limit.
+    pub fn check(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        clients.checks_since_prune += 1;
+        if clients.checks_since_prune >= PRUNE_EVERY {
+            clients
+                .windows
+                .retain(|_, (start, _)| now.duration_since(*start) < self.window);
+            clients.checks_since_prune = 0;
+        }
+
+        let (start, count) = clients.windows.entry(ip).or_insert((now, 0));
+        if now.duration_since(*start) >= self.window {
+            (*start, *count) = (now, 0);
+        }
+        if *count >= self.limit {
+            return Err(self.window - now.duration_since(*start));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+/// Middleware answering 429 once a client exceeds the limit.
+pub async fn limit_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let limiter = req.app_data::<web::Data<RateLimiter>>().cloned();
+    if let (Some(limiter), Some(addr)) = (limiter, req.peer_addr()) {
+        if let Err(wait) = limiter.check(addr.ip(), Instant::now()) {
+            let mut res = req.error_response(error::ErrorTooManyRequests(
+                "Too many requests, please slow down",
+            ));
+            res.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(wait.as_secs().max(1)),
+            );
+            return Ok(res.map_into_right_body());
+        }
+    }
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_each_client_within_a_window() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(10));
+        let (a, b) = ([10, 0, 0, 1].into(), [10, 0, 0, 2].into());
+        let now = Instant::now();
+
+        assert_eq!(limiter.check(a, now), Ok(()));
+        assert_eq!(limiter.check(a, now), Ok(()));
+        assert_eq!(
+            limiter.check(a, now + Duration::from_secs(4)),
+            Err(Duration::from_secs(6))
+        );
+        assert_eq!(limiter.check(b, now), Ok(()));
+        assert_eq!(limiter.check(a, now + Duration::from_secs(10)), Ok(()));
+    }
+
+    #[test]
+    fn forgets_idle_clients_every_so_often() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(10));
+        let now = Instant::now();
+        limiter.check([10, 0, 0, 1].into(), now).unwrap();
+
+        let later = now + Duration::from_secs(10);
+        let busy = [10, 0, 0, 2].into();
+        for _ in 1..PRUNE_EVERY {
+            let _ = limiter.check(busy, later);
+        }
+        let clients = limiter.clients.lock().unwrap();
+        assert_eq!(clients.windows.len(), 1);
+        assert!(clients.windows.contains_key(&busy));
+    }
+}
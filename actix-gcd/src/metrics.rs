@@ -0,0 +1,87 @@
+// Prometheus metrics: request counts and latencies per route, recorded by
+// middleware, plus a count of the calculations performed.
+
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error, HttpResponse,
+};
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, Encoder, HistogramVec, IntCounterVec,
+    TextEncoder,
+};
+
+static HTTP_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "http_requests_total",
+        "Requests handled, by method, route and status.",
+        &["method", "route", "status"]
+    )
+    .expect("metric can be registered")
+});
+
+static HTTP_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "http_request_duration_seconds",
+        "Time spent handling requests, by method and route.",
+        &["method", "route"]
+    )
+    .expect("metric can be registered")
+});
+
+static COMPUTATIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "computations_total",
+        "Calculations performed, by operation.",
+        &["operation"]
+    )
+    .expect("metric can be registered")
+});
+
+pub fn record_computation(operation: &str) {
+    COMPUTATIONS.with_label_values(&[operation]).inc();
+}
+
+/// Middleware timing every request. Routes are labelled by their pattern
+/// (e.g. `/api/gcd`), and anything unmatched as `other`, so the number of
+/// label values stays bounded.
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let method = req.method().to_string();
+    let start = Instant::now();
+    let res = next.call(req).await?;
+
+    let route = res
+        .request()
+        .match_pattern()
+        .unwrap_or_else(|| "other".to_string());
+    let status = res.status().as_u16().to_string();
+    HTTP_REQUESTS
+        .with_label_values(&[method.as_str(), route.as_str(), status.as_str()])
+        .inc();
+    HTTP_LATENCY
+        .with_label_values(&[method.as_str(), route.as_str()])
+        .observe(start.elapsed().as_secs_f64());
+    Ok(res)
+}
+
+pub async fn get_metrics() -> HttpResponse {
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    match encoder.encode(&prometheus::gather(), &mut body) {
+        Ok(()) => HttpResponse::Ok()
+            .content_type(encoder.format_type())
+            .body(body),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+pub async fn get_healthz() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
@@ -0,0 +1,274 @@
+// Number theory helpers shared by the HTML and JSON handlers.
+
+use serde::Serialize;
+
+/// One division `a = q*b + r` performed by the Euclidean algorithm.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct EuclidStep {
+    pub a: u64,
+    pub q: u64,
+    pub b: u64,
+    pub r: u64,
+}
+
+// Euclid's algorithm, handing every division to `record` as it goes.
+fn euclid(mut a: u64, mut b: u64, mut record: impl FnMut(EuclidStep)) -> u64 {
+    while b != 0 {
+        let (q, r) = (a / b, a % b);
+        record(EuclidStep { a, q, b, r });
+        a = b;
+        b = r;
+    }
+    a
+}
+
+pub fn gcd(a: u64, b: u64) -> u64 {
+    euclid(a, b, |_| {})
+}
+
+/// Like `gcd`, but also returns the divisions that led to the result.
+pub fn gcd_with_steps(a: u64, b: u64) -> (u64, Vec<EuclidStep>) {
+    let mut steps = Vec::new();
+    let gcd = euclid(a, b, |step| steps.push(step));
+    (gcd, steps)
+}
+
+/// Extended Euclid: returns `(g, x, y)` with `a*x + b*y = g = gcd(a, b)`.
+pub fn extended_gcd(a: u64, b: u64) -> (u64, i128, i128) {
+    let (mut old_r, mut r) = (a as i128, b as i128);
+    let (mut old_x, mut x) = (1i128, 0i128);
+    let (mut old_y, mut y) = (0i128, 1i128);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+    (old_r as u64, old_x, old_y)
+}
+
+/// Continued fraction expansion `[q0; q1, q2, ...]` of `a / b`. Its terms
+/// are the quotients Euclid's algorithm produces along the way.
+pub fn continued_fraction(a: u64, b: u64) -> Vec<u64> {
+    let mut terms = Vec::new();
+    euclid(a, b, |step| terms.push(step.q));
+    terms
+}
+
+/// Least common multiple, or `None` when it does not fit in a `u64`.
+pub fn lcm(a: u64, b: u64) -> Option<u64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// Inverse of `a` modulo `m`, if `a` and `m` are coprime.
+pub fn mod_inverse(a: u64, m: u64) -> Option<u64> {
+    if m == 0 {
+        return None;
+    }
+    let (g, x, _) = extended_gcd(a % m, m);
+    if g != 1 {
+        return None;
+    }
+    Some(x.rem_euclid(m as i128) as u64)
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+// These witnesses make Miller-Rabin deterministic for every u64.
+const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+pub fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for p in WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let (mut d, mut s) = (n - 1, 0);
+    while d.is_multiple_of(2) {
+        d /= 2;
+        s += 1;
+    }
+    'witness: for a in WITNESSES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+// Finds a non-trivial divisor of an odd composite `n`.
+fn pollard_rho(n: u64) -> u64 {
+    for c in 1.. {
+        let f = |x: u64| ((x as u128 * x as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = gcd(x.abs_diff(y), n);
+        }
+        if d != n {
+            return d;
+        }
+    }
+    unreachable!("pollard rho ran out of constants")
+}
+
+fn split(n: u64, primes: &mut Vec<u64>) {
+    if n == 1 {
+        return;
+    }
+    if is_prime(n) {
+        primes.push(n);
+        return;
+    }
+    let d = pollard_rho(n);
+    split(d, primes);
+    split(n / d, primes);
+}
+
+// Trial division handles small factors cheaply; whatever is left over is
+// split with Pollard's rho.
+const TRIAL_LIMIT: u64 = 1000;
+
+/// Prime factorization of `n` as `(prime, exponent)` pairs in ascending
+/// order. Returns an empty list for 0 and 1.
+pub fn factorize(mut n: u64) -> Vec<(u64, u32)> {
+    if n < 2 {
+        return Vec::new();
+    }
+    let mut primes = Vec::new();
+    let mut p = 2;
+    while p < TRIAL_LIMIT && p * p <= n {
+        while n.is_multiple_of(p) {
+            primes.push(p);
+            n /= p;
+        }
+        p += if p == 2 { 1 } else { 2 };
+    }
+    split(n, &mut primes);
+    primes.sort_unstable();
+
+    let mut factors: Vec<(u64, u32)> = Vec::new();
+    for prime in primes {
+        match factors.last_mut() {
+            Some((last, exponent)) if *last == prime => *exponent += 1,
+            _ => factors.push((prime, 1)),
+        }
+    }
+    factors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcd_steps_trace_each_division() {
+        let (g, steps) = gcd_with_steps(1071, 462);
+        assert_eq!(g, 21);
+        let divisions: Vec<_> = steps.iter().map(|s| (s.a, s.q, s.b, s.r)).collect();
+        assert_eq!(
+            divisions,
+            vec![(1071, 2, 462, 147), (462, 3, 147, 21), (147, 7, 21, 0)]
+        );
+        for step in &steps {
+            assert_eq!(step.a, step.q * step.b + step.r);
+        }
+        assert_eq!(gcd_with_steps(5, 0), (5, vec![]));
+    }
+
+    #[test]
+    fn extended_gcd_satisfies_bezout() {
+        for (a, b) in [(240, 46), (46, 240), (17, 5), (7, 0), (0, 7), (u64::MAX, 3)] {
+            let (g, x, y) = extended_gcd(a, b);
+            assert_eq!(g, gcd(a, b));
+            assert_eq!(a as i128 * x + b as i128 * y, g as i128);
+        }
+    }
+
+    #[test]
+    fn continued_fraction_uses_euclid_quotients() {
+        assert_eq!(continued_fraction(415, 93), vec![4, 2, 6, 7]);
+        assert_eq!(continued_fraction(12, 18), vec![0, 1, 2]);
+        assert_eq!(continued_fraction(7, 1), vec![7]);
+    }
+
+    #[test]
+    fn lcm_detects_overflow() {
+        assert_eq!(lcm(4, 6), Some(12));
+        assert_eq!(lcm(21, 6), Some(42));
+        assert_eq!(lcm(u64::MAX, 1), Some(u64::MAX));
+        assert_eq!(lcm(u64::MAX, 2), None);
+    }
+
+    #[test]
+    fn mod_inverse_only_exists_for_coprime_values() {
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(10, 17), Some(12));
+        assert_eq!(mod_inverse(6, 9), None);
+        assert_eq!(mod_inverse(5, 0), None);
+    }
+
+    #[test]
+    fn primality_is_exact_for_large_values() {
+        assert!(is_prime(2));
+        assert!(is_prime(1_000_000_007));
+        assert!(is_prime(18_446_744_073_709_551_557));
+        assert!(!is_prime(1));
+        assert!(!is_prime(561));
+        assert!(!is_prime(3_215_031_751));
+    }
+
+    #[test]
+    fn factorize_combines_trial_division_and_pollard_rho() {
+        assert_eq!(factorize(1), vec![]);
+        assert_eq!(factorize(360), vec![(2, 3), (3, 2), (5, 1)]);
+        assert_eq!(factorize(97), vec![(97, 1)]);
+        // Both factors are above the trial division limit.
+        assert_eq!(
+            factorize(1_000_000_007 * 998_244_353),
+            vec![(998_244_353, 1), (1_000_000_007, 1)]
+        );
+        assert_eq!(
+            factorize(u64::MAX),
+            vec![
+                (3, 1),
+                (5, 1),
+                (17, 1),
+                (257, 1),
+                (641, 1),
+                (65537, 1),
+                (6_700_417, 1)
+            ]
+        );
+    }
+}
@@ -0,0 +1,62 @@
+// Askama templates for the HTML pages. They all extend `layout.html` and
+// escape whatever they interpolate.
+
+use actix_web::{http::StatusCode, HttpResponse};
+use askama::Template;
+use serde::Serialize;
+
+use crate::history::CalcRecord;
+use crate::{FactorizeResponse, GcdResponse, LcmResponse, ModInvResponse};
+
+#[derive(Template)]
+#[template(path = "index.html")]
+pub struct IndexTemplate;
+
+/// A finished calculation, shown by `result.html`.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Calculation {
+    Gcd(GcdResponse),
+    Lcm(LcmResponse),
+    Factorize(FactorizeResponse),
+    ModInv(ModInvResponse),
+}
+
+impl Calculation {
+    fn title(&self) -> &'static str {
+        match self {
+            Calculation::Gcd(_) => "GCD Result",
+            Calculation::Lcm(_) => "LCM Result",
+            Calculation::Factorize(_) => "Prime Factorization",
+            Calculation::ModInv(_) => "Modular Inverse",
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "result.html")]
+pub struct ResultTemplate {
+    pub calculation: Calculation,
+}
+
+#[derive(Template)]
+#[template(path = "history.html")]
+pub struct HistoryTemplate {
+    pub records: Vec<CalcRecord>,
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+pub struct ErrorTemplate<'a> {
+    pub title: &'a str,
+    pub message: &'a str,
+}
+
+pub fn render(status: StatusCode, template: &impl Template) -> HttpResponse {
+    match template.render() {
+        Ok(body) => HttpResponse::build(status)
+            .content_type("text/html; charset=utf-8")
+            .body(body),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
@@ -0,0 +1,129 @@
+// Live calculator over a WebSocket. Each text message is one calculation,
+// e.g. `{"op":"gcd","a":12,"b":18}` or `{"op":"factorize","n":360}`; an
+// optional `id` is echoed back so replies can be matched to requests, since
+// they are sent as each calculation finishes rather than in order.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use actix_web::{rt, web, HttpRequest, HttpResponse};
+use actix_ws::{Message, Session};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::history::History;
+use crate::limits::RateLimiter;
+use crate::{
+    factorize_result, lcm_result, limits, modinv_result, recorded_gcd_result, FactorizeParameters,
+    GcdParameters, ModInvParameters,
+};
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum Operation {
+    Gcd(GcdParameters),
+    Lcm(GcdParameters),
+    Factorize(FactorizeParameters),
+    Modinv(ModInvParameters),
+}
+
+#[derive(Deserialize)]
+struct Request {
+    id: Option<Value>,
+    #[serde(flatten)]
+    operation: Operation,
+}
+
+fn run(operation: &Operation, history: &History) -> Result<Value, String> {
+    let result = match operation {
+        Operation::Gcd(params) => serde_json::to_value(recorded_gcd_result(params, history)?),
+        Operation::Lcm(params) => serde_json::to_value(lcm_result(params)?),
+        Operation::Factorize(params) => serde_json::to_value(factorize_result(params)?),
+        Operation::Modinv(params) => serde_json::to_value(modinv_result(params)?),
+    };
+    result.map_err(|err| err.to_string())
+}
+
+fn reply(id: Option<Value>, result: Result<Value, String>) -> String {
+    match result {
+        Ok(result) => json!({ "id": id, "result": result }),
+        Err(error) => json!({ "id": id, "error": error }),
+    }
+    .to_string()
+}
+
+// Calculations one connection may have running at once; further messages
+// are answered with an error until one finishes.
+const MAX_IN_FLIGHT: usize = 4;
+
+// Runs one calculation on the blocking pool and sends its reply, so a slow
+// factorization does not hold up the other messages on the connection. The
+// permit is held until the reply is sent.
+async fn calculate(
+    request: Request,
+    mut session: Session,
+    history: web::Data<History>,
+    _permit: OwnedSemaphorePermit,
+) {
+    let id = request.id;
+    let operation = request.operation;
+    if let Ok(result) = web::block(move || run(&operation, &history)).await {
+        let _ = session.text(reply(id, result)).await;
+    }
+}
+
+pub async fn ws(
+    req: HttpRequest,
+    body: web::Payload,
+    history: web::Data<History>,
+) -> actix_web::Result<HttpResponse> {
+    let (response, mut session, stream) = actix_ws::handle(&req, body)?;
+    let mut stream = stream.max_frame_size(limits::MAX_FORM_BYTES);
+    let limiter = req.app_data::<web::Data<RateLimiter>>().cloned();
+    let peer = req.peer_addr().map(|addr| addr.ip());
+    let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+
+    rt::spawn(async move {
+        while let Some(Ok(msg)) = stream.recv().await {
+            match msg {
+                Message::Text(text) => {
+                    let request: Request = match serde_json::from_str(&text) {
+                        Ok(request) => request,
+                        Err(err) => {
+                            let _ = session.text(reply(None, Err(err.to_string()))).await;
+                            continue;
+                        }
+                    };
+                    // Every message counts against the client's rate limit,
+                    // not just the upgrade request.
+                    if let (Some(limiter), Some(ip)) = (&limiter, peer) {
+                        if limiter.check(ip, Instant::now()).is_err() {
+                            let error = "Too many requests, please slow down".to_string();
+                            let _ = session.text(reply(request.id, Err(error))).await;
+                            continue;
+                        }
+                    }
+                    match in_flight.clone().try_acquire_owned() {
+                        Ok(permit) => {
+                            rt::spawn(calculate(request, session.clone(), history.clone(), permit));
+                        }
+                        Err(_) => {
+                            let error = "Too many calculations in progress".to_string();
+                            let _ = session.text(reply(request.id, Err(error))).await;
+                        }
+                    }
+                }
+                Message::Ping(bytes) if session.pong(&bytes).await.is_err() => return,
+                Message::Close(reason) => {
+                    let _ = session.close(reason).await;
+                    return;
+                }
+                _ => {}
+            }
+        }
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use actix_gcd::{app, history::History, limits::RateLimiter};
+use actix_web::{
+    body::MessageBody,
+    dev::ServiceResponse,
+    http::{header, StatusCode},
+    test::{self, TestRequest},
+    web,
+};
+use serde_json::{json, Value};
+
+fn history() -> web::Data<History> {
+    web::Data::new(History::new(10))
+}
+
+fn limiter() -> web::Data<RateLimiter> {
+    web::Data::new(RateLimiter::new(100, Duration::from_secs(60)))
+}
+
+fn content_type(res: &ServiceResponse<impl MessageBody>) -> String {
+    res.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[actix_web::test]
+async fn gcd_form_renders_the_result_page() {
+    let app = test::init_service(app(history(), limiter())).await;
+    let req = TestRequest::post()
+        .uri("/gcd")
+        .set_form([("numbers", "12, 18, 24")])
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(content_type(&res).starts_with("text/html"));
+
+    let body = test::read_body(res).await;
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("12, 18 and 24 is <b>6</b>"));
+}
+
+#[actix_web::test]
+async fn gcd_api_returns_json_and_records_history() {
+    let history = history();
+    let app = test::init_service(app(history.clone(), limiter())).await;
+
+    let req = TestRequest::get().uri("/api/gcd?a=240&b=46").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["gcd"], 2);
+    assert_eq!(body["numbers"], json!([240, 46]));
+    assert_eq!(body["bezout"], json!({ "x": -9, "y": 47 }));
+
+    let req = TestRequest::post()
+        .uri("/api/gcd")
+        .set_json(json!({ "numbers": [4, 6, 8], "show_steps": true }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["gcd"], 2);
+    assert!(body["steps"].is_array());
+    assert!(body.get("bezout").is_none());
+
+    assert_eq!(history.recent(10).len(), 2);
+}
+
+#[actix_web::test]
+async fn two_number_requests_keep_their_original_shape() {
+    let app = test::init_service(app(history(), limiter())).await;
+
+    let req = TestRequest::get().uri("/api/gcd?a=240&b=46").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        (&body["a"], &body["b"], &body["gcd"]),
+        (&json!(240), &json!(46), &json!(2))
+    );
+
+    let req = TestRequest::post()
+        .uri("/api/lcm")
+        .set_json(json!({ "a": 4, "b": 6 }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        (&body["a"], &body["b"], &body["lcm"]),
+        (&json!(4), &json!(6), &json!(12))
+    );
+
+    let req = TestRequest::get().uri("/api/gcd?numbers=4,6").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(body.get("a").is_none() && body.get("b").is_none());
+}
+
+#[actix_web::test]
+async fn zero_input_is_rejected_in_both_formats() {
+    let app = test::init_service(app(history(), limiter())).await;
+
+    let req = TestRequest::post()
+        .uri("/gcd")
+        .set_form([("a", "0"), ("b", "12")])
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = test::read_body(res).await;
+    assert!(String::from_utf8_lossy(&body).contains("Cannot compute GCD for zero values"));
+
+    let req = TestRequest::get().uri("/api/gcd?a=0&b=12").to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(
+        body,
+        json!({ "error": "Cannot compute GCD for zero values" })
+    );
+}
+
+#[actix_web::test]
+async fn malformed_input_gets_a_styled_error() {
+    let app = test::init_service(app(history(), limiter())).await;
+
+    let req = TestRequest::post()
+        .uri("/gcd")
+        .set_form([("numbers", "12, twelve")])
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(content_type(&res).starts_with("text/html"));
+    let body = test::read_body(res).await;
+    assert!(String::from_utf8_lossy(&body).contains("invalid number `twelve`"));
+
+    let req = TestRequest::get().uri("/api/lcm?a=x&b=2").to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(res).await;
+    assert!(body["error"].as_str().unwrap().contains("invalid digit"));
+
+    let req = TestRequest::post()
+        .uri("/gcd")
+        .insert_header((header::CONTENT_TYPE, "application/x-www-form-urlencoded"))
+        .set_payload(format!("numbers={}", "1".repeat(5000)))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[actix_web::test]
+async fn form_routes_answer_json_when_asked() {
+    let app = test::init_service(app(history(), limiter())).await;
+
+    let req = TestRequest::post()
+        .uri("/factorize")
+        .insert_header((header::ACCEPT, "application/json"))
+        .set_form([("n", "360")])
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(content_type(&res).starts_with("application/json"));
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["factors"][0], json!({ "prime": 2, "exponent": 3 }));
+
+    let req = TestRequest::post()
+        .uri("/modinv")
+        .insert_header((header::ACCEPT, "text/html, application/json;q=0.9"))
+        .set_form([("a", "3"), ("m", "11")])
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(content_type(&res).starts_with("text/html"));
+
+    let req = TestRequest::get()
+        .uri("/missing")
+        .insert_header((header::ACCEPT, "application/json"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(content_type(&res).starts_with("application/json"));
+
+    let req = TestRequest::get().uri("/missing").to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(content_type(&res).starts_with("text/html"));
+}
+
+#[actix_web::test]
+async fn clients_over_the_limit_are_turned_away() {
+    let limiter = web::Data::new(RateLimiter::new(1, Duration::from_secs(60)));
+    let app = test::init_service(app(history(), limiter)).await;
+    let request = |uri| {
+        TestRequest::get()
+            .uri(uri)
+            .peer_addr("10.0.0.1:4000".parse().unwrap())
+            .to_request()
+    };
+
+    let res = test::call_service(&app, request("/api/gcd?a=12&b=18")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, request("/api/lcm?a=4&b=6")).await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(res.headers().contains_key(header::RETRY_AFTER));
+
+    // Health checks are never throttled.
+    for _ in 0..3 {
+        let res = test::call_service(&app, request("/healthz")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS questions (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    tags TEXT[]
+);
+
+CREATE TABLE IF NOT EXISTS answers (
+    id TEXT PRIMARY KEY,
+    content TEXT NOT NULL,
+    question_id TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS answers_question_id_idx ON answers (question_id);
@@ -0,0 +1,2 @@
+ALTER TABLE questions ADD COLUMN IF NOT EXISTS account_id TEXT;
+ALTER TABLE answers ADD COLUMN IF NOT EXISTS account_id TEXT;
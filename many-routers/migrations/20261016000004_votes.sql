@@ -0,0 +1,9 @@
+ALTER TABLE questions ADD COLUMN IF NOT EXISTS score BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE questions ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ;
+
+CREATE TABLE IF NOT EXISTS votes (
+    question_id TEXT NOT NULL,
+    account_id TEXT NOT NULL,
+    value SMALLINT NOT NULL,
+    PRIMARY KEY (question_id, account_id)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE questions ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE answers ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
@@ -0,0 +1,3 @@
+ALTER TABLE questions ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ;
+ALTER TABLE answers ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ;
+ALTER TABLE answers ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ;
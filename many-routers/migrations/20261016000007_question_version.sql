@@ -0,0 +1 @@
+ALTER TABLE questions ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 0;
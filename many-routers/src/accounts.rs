@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use warp::{Rejection, Reply};
+
+use crate::{
+    auth::{normalize_email, Account, AccountId, Admins, Session},
+    pagination::{extract_pagination, paginate, Page},
+    store::Store,
+    validation::validate_profile,
+    Answer, Error, Question,
+};
+
+// What an account looks like from the outside; the password hash never
+// leaves the store.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Profile {
+    id: AccountId,
+    email: String,
+    display_name: Option<String>,
+}
+
+impl From<&Account> for Profile {
+    fn from(account: &Account) -> Self {
+        Profile {
+            id: account.id.clone(),
+            email: account.email.clone(),
+            display_name: account.display_name.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct UpdatedProfile {
+    email: String,
+    display_name: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/accounts/me",
+    responses(
+        (status = 200, description = "The caller's profile", body = Profile),
+        (status = 401, description = "Missing or invalid token")
+    ),
+    security(("token" = [])),
+    tag = "accounts"
+)]
+pub async fn get_profile(session: Session, store: Store) -> Result<impl Reply, Rejection> {
+    match store.accounts.read().await.get(&session.account_id) {
+        Some(account) => Ok(warp::reply::json(&Profile::from(account))),
+        // A valid token for an account that is gone.
+        None => Err(warp::reject::custom(Error::Unauthorized)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/accounts/me",
+    request_body = UpdatedProfile,
+    responses(
+        (status = 200, description = "The updated profile", body = Profile),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "The email belongs to an admin"),
+        (status = 409, description = "Another account already uses this email"),
+        (status = 422, description = "Invalid email or display name")
+    ),
+    security(("token" = [])),
+    tag = "accounts"
+)]
+pub async fn update_profile(
+    session: Session,
+    admins: Admins,
+    store: Store,
+    updated: UpdatedProfile,
+) -> Result<impl Reply, Rejection> {
+    // Stored the way register and login look it up, or the account could
+    // never log in again.
+    let email = normalize_email(&updated.email);
+    validate_profile(&email, updated.display_name.as_deref())?;
+
+    let mut accounts = store.accounts.write().await;
+    if accounts
+        .values()
+        .any(|a| a.email == email && a.id != session.account_id)
+    {
+        return Err(warp::reject::custom(Error::AccountExists));
+    }
+    let account = accounts
+        .get_mut(&session.account_id)
+        .ok_or(Error::Unauthorized)?;
+    // Admin rights follow the email, so only an admin may keep one.
+    if email != account.email && admins.reserves(&email) {
+        return Err(warp::reject::custom(Error::ReservedEmail));
+    }
+
+    let new = Account {
+        email,
+        display_name: updated.display_name,
+        ..account.clone()
+    };
+    store.save_account(&new).await?;
+    *account = new;
+    Ok(warp::reply::json(&Profile::from(&*account)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/accounts/me/questions",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size, default 20, at most 100"),
+        ("offset" = Option<usize>, Query, description = "Number of questions to skip")
+    ),
+    responses(
+        (status = 200, description = "The caller's questions, newest first", body = Page<Question>),
+        (status = 400, description = "Malformed pagination"),
+        (status = 401, description = "Missing or invalid token")
+    ),
+    security(("token" = [])),
+    tag = "accounts"
+)]
+pub async fn my_questions(
+    params: HashMap<String, String>,
+    session: Session,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    let pagination = extract_pagination(&params)?;
+    let ids = store
+        .account_questions
+        .read()
+        .await
+        .get(&session.account_id)
+        .cloned()
+        .unwrap_or_default();
+    let questions = store.questions.read().await;
+    let mut res: Vec<Question> = ids
+        .iter()
+        .filter_map(|id| questions.get(id))
+        .filter(|q| q.deleted_at.is_none())
+        .cloned()
+        .collect();
+    res.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(a.id.0.cmp(&b.id.0)));
+    Ok(warp::reply::json(&paginate(res, &pagination)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/accounts/me/answers",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size, default 20, at most 100"),
+        ("offset" = Option<usize>, Query, description = "Number of answers to skip")
+    ),
+    responses(
+        (status = 200, description = "The caller's answers, newest first", body = Page<Answer>),
+        (status = 400, description = "Malformed pagination"),
+        (status = 401, description = "Missing or invalid token")
+    ),
+    security(("token" = [])),
+    tag = "accounts"
+)]
+pub async fn my_answers(
+    params: HashMap<String, String>,
+    session: Session,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    let pagination = extract_pagination(&params)?;
+    let ids = store
+        .account_answers
+        .read()
+        .await
+        .get(&session.account_id)
+        .cloned()
+        .unwrap_or_default();
+    let answers = store.answers.read().await;
+    let mut res: Vec<Answer> = ids
+        .iter()
+        .filter_map(|id| answers.get(id))
+        .filter(|a| a.deleted_at.is_none())
+        .cloned()
+        .collect();
+    res.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(a.id.0.cmp(&b.id.0)));
+    Ok(warp::reply::json(&paginate(res, &pagination)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(account_id: &str) -> Session {
+        serde_json::from_value(serde_json::json!({ "account_id": account_id, "exp": 0 })).unwrap()
+    }
+
+    async fn body(reply: impl Reply) -> serde_json::Value {
+        let bytes = warp::hyper::body::to_bytes(reply.into_response().into_body())
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn lists_the_callers_profile_questions_and_answers() {
+        let store = Store::new();
+        let account = Account {
+            id: AccountId("user".to_string()),
+            email: "user@example.com".to_string(),
+            password: "hash".to_string(),
+            display_name: None,
+        };
+        store
+            .accounts
+            .write()
+            .await
+            .insert(account.id.clone(), account);
+        let questions: Vec<Question> = serde_json::from_value(serde_json::json!([
+            { "id": "Q1", "title": "Older", "content": "c", "account_id": "user",
+              "created_at": "2024-01-01T00:00:00Z" },
+            { "id": "Q2", "title": "Newer", "content": "c", "account_id": "user",
+              "created_at": "2024-02-01T00:00:00Z" },
+            { "id": "Q3", "title": "Someone else's", "content": "c", "account_id": "other" }
+        ]))
+        .unwrap();
+        for q in questions {
+            store.reindex_owner_questions(None, Some(&q)).await;
+            store.questions.write().await.insert(q.id.clone(), q);
+        }
+        let answer: Answer = serde_json::from_value(serde_json::json!({
+            "id": "A1", "content": "Use Rc", "question_id": "Q3", "account_id": "user"
+        }))
+        .unwrap();
+        store.reindex_owner_answers(None, Some(&answer)).await;
+        store
+            .answers
+            .write()
+            .await
+            .insert(answer.id.clone(), answer);
+
+        let profile = body(get_profile(session("user"), store.clone()).await.unwrap()).await;
+        assert_eq!(profile["email"], "user@example.com");
+        assert!(profile.get("password").is_none());
+
+        let page = body(
+            my_questions(HashMap::new(), session("user"), store.clone())
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["items"][0]["id"], "Q2");
+        assert_eq!(page["items"][1]["id"], "Q1");
+
+        let page = body(
+            my_answers(HashMap::new(), session("user"), store.clone())
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["items"][0]["content"], "Use Rc");
+
+        // A valid token for an account that is gone.
+        assert!(get_profile(session("gone"), store).await.is_err());
+    }
+}
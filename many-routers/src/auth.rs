@@ -0,0 +1,275 @@
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+use crate::{store::Store, validation::validate_credentials, Error};
+
+const TOKEN_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash, ToSchema)]
+pub struct AccountId(pub String);
+
+// Serialized only into the store snapshot; clients see a `Profile`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Account {
+    pub id: AccountId,
+    pub email: String,
+    pub password: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct Credentials {
+    email: String,
+    password: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Session {
+    pub account_id: AccountId,
+    exp: u64,
+}
+
+// Secret used to sign and verify session tokens, shared by the login
+// handler and the auth filter.
+#[derive(Clone)]
+pub struct TokenKey(Arc<Vec<u8>>);
+
+impl TokenKey {
+    // Reads TOKEN_SECRET, or generates a random key so tokens simply stop
+    // being valid across restarts when none is configured.
+    pub fn from_env() -> Self {
+        let secret = match std::env::var("TOKEN_SECRET") {
+            Ok(secret) => secret.into_bytes(),
+            Err(_) => {
+                use argon2::password_hash::rand_core::RngCore;
+                let mut secret = vec![0u8; 32];
+                OsRng.fill_bytes(&mut secret);
+                secret
+            }
+        };
+        TokenKey(Arc::new(secret))
+    }
+
+    fn issue(&self, account_id: &AccountId) -> Result<String, Error> {
+        let exp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            + TOKEN_LIFETIME;
+        let session = Session {
+            account_id: account_id.clone(),
+            exp: exp.as_secs(),
+        };
+        jsonwebtoken::encode(
+            &Header::default(),
+            &session,
+            &EncodingKey::from_secret(&self.0),
+        )
+        .map_err(|_| Error::Unauthorized)
+    }
+
+    fn verify(&self, token: &str) -> Result<Session, Error> {
+        jsonwebtoken::decode::<Session>(
+            token,
+            &DecodingKey::from_secret(&self.0),
+            &Validation::default(),
+        )
+        .map(|data| data.claims)
+        .map_err(|_| Error::Unauthorized)
+    }
+}
+
+// Accounts listed in --admin-emails. Admin rights are looked up per request
+// from the account's email, so they are not baked into issued tokens. The
+// listed addresses can't be claimed through the API, only by accounts that
+// already had them when the list was configured.
+#[derive(Clone, Default)]
+pub struct Admins(Arc<HashSet<String>>);
+
+impl Admins {
+    pub fn new(emails: Vec<String>) -> Self {
+        Admins(Arc::new(
+            emails
+                .into_iter()
+                .map(|e| e.trim().to_lowercase())
+                .collect(),
+        ))
+    }
+
+    pub fn reserves(&self, email: &str) -> bool {
+        self.0.contains(&email.to_lowercase())
+    }
+
+    pub async fn check(&self, store: &Store, session: &Session) -> Result<(), Error> {
+        let accounts = store.accounts.read().await;
+        match accounts.get(&session.account_id) {
+            Some(account) if self.0.contains(&account.email.to_lowercase()) => Ok(()),
+            _ => Err(Error::NotAdmin),
+        }
+    }
+}
+
+fn hash_password(password: &str) -> Result<String, Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(Error::Hashing)
+}
+
+fn verify_password(hash: &str, password: &str) -> Result<bool, Error> {
+    let hash = PasswordHash::new(hash).map_err(Error::Hashing)?;
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .is_ok())
+}
+
+// Emails are stored and compared in this form, so case and stray spaces
+// can't create a second account for the same address.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+pub fn auth(key: TokenKey) -> impl Filter<Extract = (Session,), Error = Rejection> + Clone {
+    warp::header::<String>("Authorization").and_then(move |header: String| {
+        let key = key.clone();
+        async move {
+            let token = header.strip_prefix("Bearer ").unwrap_or(&header);
+            key.verify(token).map_err(warp::reject::custom)
+        }
+    })
+}
+
+// Like `auth`, but lets anonymous requests through as `None`. A token that is
+// present must still be valid.
+pub fn optional_auth(
+    key: TokenKey,
+) -> impl Filter<Extract = (Option<Session>,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("Authorization").and_then(move |header: Option<String>| {
+        let key = key.clone();
+        async move {
+            match header {
+                Some(header) => {
+                    let token = header.strip_prefix("Bearer ").unwrap_or(&header);
+                    key.verify(token).map(Some).map_err(warp::reject::custom)
+                }
+                None => Ok(None),
+            }
+        }
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/registration",
+    request_body = Credentials,
+    responses(
+        (status = 201, description = "Account added"),
+        (status = 403, description = "The email belongs to an admin"),
+        (status = 409, description = "An account with this email already exists"),
+        (status = 422, description = "Malformed email or a password that is too short")
+    ),
+    tag = "accounts"
+)]
+pub async fn register(
+    store: Store,
+    admins: Admins,
+    credentials: Credentials,
+) -> Result<impl Reply, Rejection> {
+    let email = normalize_email(&credentials.email);
+    validate_credentials(&email, &credentials.password)?;
+    if admins.reserves(&email) {
+        return Err(warp::reject::custom(Error::ReservedEmail));
+    }
+
+    // Argon2 is deliberately slow, so it runs off the async workers and
+    // before the accounts lock is taken.
+    let password = tokio::task::spawn_blocking(move || hash_password(&credentials.password))
+        .await
+        .expect("password hashing panicked")?;
+    let account = Account {
+        id: AccountId(Uuid::new_v4().to_string()),
+        email,
+        password,
+        display_name: None,
+    };
+
+    let mut accounts = store.accounts.write().await;
+    if accounts.values().any(|a| a.email == account.email) {
+        return Err(warp::reject::custom(Error::AccountExists));
+    }
+    store.save_account(&account).await?;
+    accounts.insert(account.id.clone(), account);
+    Ok(warp::reply::with_status(
+        "Account added",
+        StatusCode::CREATED,
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/login",
+    request_body = Credentials,
+    responses(
+        (status = 200, description = "A token valid for 24 hours", body = String),
+        (status = 401, description = "Wrong email or password")
+    ),
+    tag = "accounts"
+)]
+pub async fn login(
+    store: Store,
+    key: TokenKey,
+    credentials: Credentials,
+) -> Result<impl Reply, Rejection> {
+    let email = normalize_email(&credentials.email);
+    let (id, hash) = store
+        .accounts
+        .read()
+        .await
+        .values()
+        .find(|a| a.email == email)
+        .map(|a| (a.id.clone(), a.password.clone()))
+        .ok_or(Error::WrongPassword)?;
+
+    // Verifying is as slow as hashing, so it too stays off the async
+    // workers and outside the accounts lock.
+    let verified =
+        tokio::task::spawn_blocking(move || verify_password(&hash, &credentials.password))
+            .await
+            .expect("password verification panicked")?;
+    if !verified {
+        return Err(warp::reject::custom(Error::WrongPassword));
+    }
+    Ok(warp::reply::json(&key.issue(&id)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_email_trims_and_lowercases() {
+        assert_eq!(normalize_email("  Admin@Example.COM "), "admin@example.com");
+        assert_eq!(normalize_email("user@example.com"), "user@example.com");
+    }
+
+    #[test]
+    fn passwords_verify_against_their_hash() {
+        let hash = hash_password("secret").unwrap();
+        assert!(verify_password(&hash, "secret").unwrap());
+        assert!(!verify_password(&hash, "Secret").unwrap());
+    }
+}
@@ -0,0 +1,192 @@
+use std::{convert::Infallible, io::Write};
+
+use flate2::{write::GzEncoder, Compression};
+use warp::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    hyper::body::{self, Body, HttpBody},
+    reply::Response,
+    Filter,
+};
+
+// Whether the request's Accept-Encoding allows gzip; `gzip;q=0` refuses it.
+pub fn accepts_gzip() -> impl Filter<Extract = (bool,), Error = Infallible> + Clone {
+    warp::header::headers_cloned().map(|headers: HeaderMap| {
+        headers
+            .get_all(header::ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|coding| {
+                let mut parts = coding.split(';').map(str::trim);
+                let name = parts.next().unwrap_or_default();
+                let refused = parts.any(|p| {
+                    p.strip_prefix("q=")
+                        .and_then(|q| q.parse::<f32>().ok())
+                        .is_some_and(|q| q == 0.0)
+                });
+                (name.eq_ignore_ascii_case("gzip") || name == "*") && !refused
+            })
+    })
+}
+
+fn is_compressible(response: &Response) -> bool {
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    content_type.starts_with("text/")
+        || content_type.contains("json")
+        || content_type.contains("javascript")
+        || content_type.contains("xml")
+}
+
+const GZIP_TAG_SUFFIX: &str = "-gzip";
+
+// The gzipped bytes differ from the ones the ETag was computed over, so the
+// gzipped representation gets its own strong tag: `"abc"` becomes
+// `"abc-gzip"`. Weak tags already promise no byte-for-byte match.
+fn tag_gzip_etag(headers: &mut HeaderMap) {
+    let Some(etag) = headers.get(header::ETAG).and_then(|v| v.to_str().ok()) else {
+        return;
+    };
+    let Some(opaque) = etag.strip_prefix('"').and_then(|e| e.strip_suffix('"')) else {
+        return;
+    };
+    if let Ok(tagged) = HeaderValue::from_str(&format!("\"{}{}\"", opaque, GZIP_TAG_SUFFIX)) {
+        headers.insert(header::ETAG, tagged);
+    }
+}
+
+// The tag of the uncompressed representation a client's tag stands for, so
+// conditional requests match whichever of the two the client was sent.
+pub fn identity_etag(tag: &str) -> String {
+    match tag
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .and_then(|t| t.strip_suffix(GZIP_TAG_SUFFIX))
+    {
+        Some(opaque) => format!("\"{}\"", opaque),
+        None => tag.to_string(),
+    }
+}
+
+// Gzips text bodies of at least `threshold` bytes. Streamed bodies, whose
+// size isn't known up front, are passed through so they keep streaming.
+pub async fn gzip(response: Response, accepted: bool, threshold: usize) -> Response {
+    let size = response.body().size_hint().exact();
+    let eligible = response.status() != StatusCode::NOT_MODIFIED
+        && !response.headers().contains_key(header::CONTENT_ENCODING)
+        && is_compressible(&response)
+        && size.is_some_and(|size| size >= threshold as u64);
+    if !eligible {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    parts
+        .headers
+        .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    let bytes = match body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Cannot read response body: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    if !accepted {
+        return Response::from_parts(parts, Body::from(bytes));
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    match encoder.write_all(&bytes).and_then(|_| encoder.finish()) {
+        Ok(compressed) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            parts
+                .headers
+                .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+            tag_gzip_etag(&mut parts.headers);
+            Response::from_parts(parts, Body::from(compressed))
+        }
+        Err(e) => {
+            tracing::error!("Cannot compress response: {}", e);
+            Response::from_parts(parts, Body::from(bytes))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    async fn accepts(accept_encoding: Option<&str>) -> bool {
+        let mut request = warp::test::request();
+        if let Some(value) = accept_encoding {
+            request = request.header(header::ACCEPT_ENCODING, value);
+        }
+        request.filter(&accepts_gzip()).await.unwrap()
+    }
+
+    fn json(size: usize) -> Response {
+        let mut response = Response::new(Body::from("x".repeat(size)));
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        response
+            .headers_mut()
+            .insert(header::ETAG, HeaderValue::from_static("\"7\""));
+        response
+    }
+
+    #[tokio::test]
+    async fn reads_gzip_out_of_accept_encoding() {
+        assert!(accepts(Some("gzip")).await);
+        assert!(accepts(Some("br, GZIP;q=0.5")).await);
+        assert!(accepts(Some("*")).await);
+        assert!(!accepts(None).await);
+        assert!(!accepts(Some("br, deflate")).await);
+        assert!(!accepts(Some("gzip;q=0")).await);
+        assert!(!accepts(Some("gzip; q=0.0, br")).await);
+    }
+
+    #[tokio::test]
+    async fn compresses_large_text_bodies() {
+        let response = gzip(json(2048), true, 1024).await;
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[header::VARY], "accept-encoding");
+        assert_eq!(response.headers()[header::ETAG], "\"7-gzip\"");
+        let compressed = body::to_bytes(response.into_body()).await.unwrap();
+        let mut text = String::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "x".repeat(2048));
+    }
+
+    #[test]
+    fn gzip_tags_map_back_to_the_identity_tag() {
+        assert_eq!(identity_etag("\"7-gzip\""), "\"7\"");
+        assert_eq!(identity_etag("\"7\""), "\"7\"");
+        assert_eq!(identity_etag("*"), "*");
+    }
+
+    #[tokio::test]
+    async fn leaves_small_refused_or_binary_bodies_alone() {
+        let small = gzip(json(10), true, 1024).await;
+        assert!(!small.headers().contains_key(header::CONTENT_ENCODING));
+
+        // Still varies, since a client that accepts gzip would get it.
+        let refused = gzip(json(2048), false, 1024).await;
+        assert!(!refused.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(refused.headers()[header::VARY], "accept-encoding");
+        assert_eq!(refused.headers()[header::ETAG], "\"7\"");
+
+        let binary = gzip(Response::new(Body::from(vec![0u8; 2048])), true, 1024).await;
+        assert!(!binary.headers().contains_key(header::CONTENT_ENCODING));
+    }
+}
@@ -0,0 +1,82 @@
+use std::{net::IpAddr, path::PathBuf};
+
+use clap::Parser;
+use warp::http::Uri;
+
+// Every option can be given on the command line or through the
+// environment variable named next to it.
+#[derive(Parser, Debug)]
+#[command(about = "Question and answer API server")]
+pub struct Config {
+    /// Address to listen on
+    #[arg(long, env = "ADDRESS", default_value = "127.0.0.1")]
+    pub address: IpAddr,
+
+    /// Port to listen on
+    #[arg(long, env = "PORT", default_value_t = 3030)]
+    pub port: u16,
+
+    /// Comma-separated origins allowed to call the API from a browser, or
+    /// `*` for any; cross-origin requests are refused when empty
+    #[arg(long, env = "CORS_ORIGINS", value_delimiter = ',', value_parser = parse_origin)]
+    pub cors_origins: Vec<String>,
+
+    /// Responses of at least this many bytes are gzipped for clients that
+    /// accept it
+    #[arg(long, env = "COMPRESSION_THRESHOLD", default_value_t = 1024)]
+    pub compression_threshold: usize,
+
+    /// Log level for this crate, ignored when RUST_LOG is set
+    #[arg(long, env = "LOG_LEVEL", default_value = "info")]
+    pub log_level: String,
+
+    /// Requests per second allowed per client IP, 0 disables rate limiting
+    #[arg(long, env = "RATE_LIMIT", default_value_t = 10.0)]
+    pub rate_limit: f64,
+
+    /// Requests a client IP may send in a burst before being limited
+    #[arg(long, env = "RATE_LIMIT_BURST", default_value_t = 20)]
+    pub rate_limit_burst: u32,
+
+    /// Comma-separated emails of the accounts allowed to see and restore
+    /// everyone's deleted questions; register them before listing them here,
+    /// since listed emails can no longer be registered
+    #[arg(long, env = "ADMIN_EMAILS", value_delimiter = ',')]
+    pub admin_emails: Vec<String>,
+
+    /// Postgres connection string. The database is loaded into memory at
+    /// startup and every change is written through to it, so reads never hit
+    /// it and only one instance may use a database at a time. The store
+    /// stays in-memory when unset
+    #[arg(long, env = "DATABASE_URL")]
+    pub database_url: Option<String>,
+
+    /// File the in-memory store is restored from and saved to when no
+    /// database is used
+    #[arg(long, env = "SNAPSHOT_PATH", default_value = "store_snapshot.json")]
+    pub snapshot_path: PathBuf,
+
+    /// Seconds between snapshots, 0 to only save on shutdown
+    #[arg(long, env = "SNAPSHOT_INTERVAL", default_value_t = 30)]
+    pub snapshot_interval: u64,
+}
+
+// Accepts `*` or a bare scheme://host[:port] origin, which is all a browser
+// ever sends in the Origin header.
+fn parse_origin(origin: &str) -> Result<String, String> {
+    let origin = origin.trim();
+    if origin == "*" {
+        return Ok(origin.to_string());
+    }
+    let uri: Uri = origin
+        .parse()
+        .map_err(|e| format!("invalid origin {:?}: {}", origin, e))?;
+    let bare = uri.path_and_query().is_none_or(|p| p.as_str() == "/");
+    match (uri.scheme_str(), uri.authority()) {
+        (Some("http" | "https"), Some(_)) if bare => Ok(origin.trim_end_matches('/').to_string()),
+        _ => Err(format!(
+            "invalid origin {:?}, expected scheme://host[:port]",
+            origin
+        )),
+    }
+}
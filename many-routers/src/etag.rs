@@ -0,0 +1,99 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use serde::Serialize;
+use warp::{
+    http::{header, HeaderValue, StatusCode},
+    hyper::Body,
+    reply::Response,
+};
+
+use crate::compression::identity_etag;
+
+// Serializes `value` as JSON with an ETag derived from the body, answering
+// 304 without a body when `if_none_match` already names that tag. The 304
+// repeats the tag the client matched with, which may be the gzip variant
+// its cached 200 carried.
+pub fn json_with_etag<T: Serialize>(value: &T, if_none_match: Option<String>) -> Response {
+    let body = serde_json::to_vec(value).unwrap_or_default();
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    let etag = format!("\"{:016x}\"", hasher.finish());
+
+    // If-None-Match uses weak comparison, so W/ is ignored.
+    let matched = if_none_match.as_deref().and_then(|header| {
+        header
+            .split(',')
+            .map(str::trim)
+            .find(|tag| *tag == "*" || identity_etag(tag.strip_prefix("W/").unwrap_or(tag)) == etag)
+    });
+
+    let (mut response, etag) = if let Some(matched) = matched {
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::NOT_MODIFIED;
+        let etag = if matched == "*" {
+            etag
+        } else {
+            matched.to_string()
+        };
+        (response, etag)
+    } else {
+        let mut response = Response::new(Body::from(body));
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        (response, etag)
+    };
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn etag(response: &Response) -> String {
+        response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn the_tag_follows_the_body() {
+        let first = json_with_etag(&["a", "b"], None);
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(first.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(etag(&first), etag(&json_with_etag(&["a", "b"], None)));
+        assert_ne!(etag(&first), etag(&json_with_etag(&["a", "c"], None)));
+    }
+
+    #[test]
+    fn a_matching_if_none_match_gets_304() {
+        let tag = etag(&json_with_etag(&[1, 2], None));
+        let gzip_tag = format!("{}-gzip\"", tag.trim_end_matches('"'));
+        for (header, echoed) in [
+            (tag.clone(), tag.clone()),
+            (format!("W/{}", tag), format!("W/{}", tag)),
+            (format!("\"old\", {}", tag), tag.clone()),
+            (gzip_tag.clone(), gzip_tag),
+            ("*".to_string(), tag.clone()),
+        ] {
+            let response = json_with_etag(&[1, 2], Some(header.clone()));
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{}", header);
+            assert_eq!(etag(&response), echoed);
+        }
+    }
+
+    #[test]
+    fn a_stale_if_none_match_gets_the_body() {
+        let tag = etag(&json_with_etag(&[1, 2], None));
+        let response = json_with_etag(&[1, 2, 3], Some(tag));
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use warp::{http::StatusCode, Rejection, Reply};
+
+use crate::store::Store;
+
+const READY_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+struct Status {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+// GET /health: the process is up and serving requests.
+#[utoipa::path(
+    get,
+    path = "/health",
+    responses((status = 200, description = "The server is running")),
+    tag = "operations"
+)]
+pub async fn health() -> Result<impl Reply, Rejection> {
+    Ok(warp::reply::json(&Status {
+        status: "ok",
+        reason: None,
+    }))
+}
+
+// GET /ready: the store answers within READY_TIMEOUT, so requests won't just
+// queue up behind a stuck lock or an unreachable database.
+#[utoipa::path(
+    get,
+    path = "/ready",
+    responses(
+        (status = 200, description = "The store is reachable"),
+        (status = 503, description = "The store or its database is not responding")
+    ),
+    tag = "operations"
+)]
+pub async fn ready(store: Store) -> Result<impl Reply, Rejection> {
+    let reason = match tokio::time::timeout(READY_TIMEOUT, store.ping()).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some("store did not respond in time".to_string()),
+    };
+    let status = if reason.is_none() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&Status {
+            status: if reason.is_none() {
+                "ready"
+            } else {
+                "unavailable"
+            },
+            reason,
+        }),
+        status,
+    ))
+}
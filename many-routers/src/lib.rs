@@ -0,0 +1,1781 @@
+// The route tree is deep enough to overflow the default limit when its
+// future type is laid out.
+#![recursion_limit = "256"]
+
+use chrono::{DateTime, SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::HashMap, convert::Infallible, time::Duration};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use warp::{
+    filters::BoxedFilter,
+    filters::{body::BodyDeserializeError, cors::CorsForbidden},
+    http::{header, HeaderValue, Method, StatusCode},
+    reject::{MissingHeader, Reject},
+    reply::Response,
+    Filter, Rejection, Reply,
+};
+
+mod accounts;
+pub mod auth;
+mod compression;
+pub mod config;
+mod etag;
+mod health;
+mod markdown;
+mod metrics;
+pub mod moderation;
+mod openapi;
+mod pagination;
+mod rate_limit;
+mod similarity;
+pub mod snapshot;
+pub mod store;
+mod trace;
+mod transfer;
+mod validation;
+
+use auth::{AccountId, Admins, Session, TokenKey};
+use config::Config;
+use etag::json_with_etag;
+use moderation::{ensure_clean, Moderator};
+use pagination::{
+    extract_cursor_pagination, extract_pagination, paginate, paginate_by_cursor, Cursor,
+    CursorPage, Page,
+};
+use rate_limit::RateLimiter;
+use store::Store;
+use trace::RequestId;
+use validation::{validate_answer, validate_question, validate_tags};
+
+// Largest body POST /import accepts.
+const IMPORT_LIMIT: u64 = 16 * 1024 * 1024;
+// How alike two titles must be, by `similarity::similarity`, for a new
+// question to be held back as a likely duplicate.
+const DUPLICATE_THRESHOLD: f64 = 0.6;
+const DUPLICATES_MAX: usize = 5;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, ToSchema)]
+pub struct Question {
+    id: QuestionId,
+    title: String,
+    content: String,
+    tags: Option<Vec<String>>,
+    #[serde(default)]
+    account_id: Option<AccountId>,
+    #[serde(default)]
+    score: i64,
+    // Bumped on every edit; GET /questions/{id} sends it as the ETag and
+    // PATCH checks If-Match against it.
+    #[serde(default)]
+    version: i64,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq, Hash, ToSchema)]
+pub struct QuestionId(pub String);
+
+// The part of a question clients write; the id and bookkeeping fields are
+// the server's.
+#[derive(Deserialize, Debug, ToSchema)]
+struct NewQuestion {
+    title: String,
+    content: String,
+    tags: Option<Vec<String>>,
+}
+
+// Body of PATCH /questions/{id}: only the fields present are changed.
+#[derive(Deserialize, Debug, ToSchema)]
+struct QuestionPatch {
+    title: Option<String>,
+    content: Option<String>,
+    tags: Option<Vec<String>>,
+}
+
+impl NewQuestion {
+    fn into_question(self, id: QuestionId) -> Question {
+        Question {
+            id,
+            title: self.title,
+            content: self.content,
+            tags: self.tags,
+            ..Question::default()
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash, ToSchema)]
+pub struct AnswerId(pub String);
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct Answer {
+    id: AnswerId,
+    content: String,
+    question_id: QuestionId,
+    account_id: Option<AccountId>,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+struct NewAnswer {
+    content: String,
+    #[serde(rename = "questionId", alias = "question_id")]
+    question_id: QuestionId,
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+struct UpdatedAnswer {
+    content: String,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+struct TagCount {
+    tag: String,
+    count: usize,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+struct Duplicate {
+    id: QuestionId,
+    title: String,
+    similarity: f64,
+}
+
+// Body of the 409 from POST /questions when the title looks like one that
+// is already asked.
+#[derive(Serialize, Debug, ToSchema)]
+struct Duplicates {
+    message: &'static str,
+    duplicates: Vec<Duplicate>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Parse(std::num::ParseIntError),
+    MissingParameters,
+    InvalidParameter(&'static str),
+    QuestionNotFound,
+    AnswerNotFound,
+    TagNotFound,
+    AccountExists,
+    ReservedEmail,
+    WrongPassword,
+    Unauthorized,
+    NotOwner,
+    NotAdmin,
+    AlreadyVoted,
+    VersionMismatch,
+    Offensive(Vec<String>),
+    InvalidImport(String),
+    Validation(Vec<validation::FieldError>),
+    RateLimited(Duration),
+    Hashing(argon2::password_hash::Error),
+    #[cfg(feature = "postgres")]
+    DatabaseQuery(sqlx::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Error::Parse(ref err) => write!(f, "Cannot parse parameter: {}", err),
+            Error::MissingParameters => write!(f, "Missing parameters"),
+            Error::InvalidParameter(name) => write!(f, "Invalid value for parameter {}", name),
+            Error::QuestionNotFound => write!(f, "Question not found"),
+            Error::AnswerNotFound => write!(f, "Answer not found"),
+            Error::TagNotFound => write!(f, "Tag not found"),
+            Error::AccountExists => write!(f, "Account already exists"),
+            Error::ReservedEmail => write!(f, "This email is reserved for an admin"),
+            Error::WrongPassword => write!(f, "Wrong email or password"),
+            Error::Unauthorized => write!(f, "Invalid or expired token"),
+            Error::NotOwner => write!(f, "Not the owner of this resource"),
+            Error::NotAdmin => write!(f, "Admin access required"),
+            Error::AlreadyVoted => write!(f, "Already voted this way on this question"),
+            Error::VersionMismatch => write!(f, "Question was changed by someone else"),
+            Error::RateLimited(_) => write!(f, "Too many requests"),
+            Error::Offensive(ref words) => {
+                write!(f, "Content contains offensive words: {}", words.join(", "))
+            }
+            Error::Validation(ref errors) => {
+                let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "Invalid input: {}", errors.join("; "))
+            }
+            Error::InvalidImport(ref reason) => write!(f, "Cannot import data: {}", reason),
+            Error::Hashing(_) => write!(f, "Cannot verify password"),
+            #[cfg(feature = "postgres")]
+            Error::DatabaseQuery(_) => write!(f, "Cannot update data"),
+        }
+    }
+}
+
+impl Reject for Error {}
+
+// Body of a 422 for invalid input, listing every field that failed so a
+// client can point at each one.
+#[derive(Serialize)]
+struct ValidationFailure<'a> {
+    errors: &'a [validation::FieldError],
+    request_id: String,
+}
+
+fn return_error(r: Rejection, request_id: &RequestId) -> Response {
+    if let Some(Error::Validation(errors)) = r.find::<Error>() {
+        let body = ValidationFailure {
+            errors,
+            request_id: request_id.to_string(),
+        };
+        return warp::reply::with_status(
+            warp::reply::json(&body),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        )
+        .into_response();
+    }
+
+    let (message, status) = if let Some(error) = r.find::<Error>() {
+        let status = match error {
+            Error::Parse(_) | Error::MissingParameters | Error::InvalidParameter(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            Error::QuestionNotFound | Error::AnswerNotFound | Error::TagNotFound => {
+                StatusCode::NOT_FOUND
+            }
+            Error::AccountExists | Error::AlreadyVoted => StatusCode::CONFLICT,
+            Error::WrongPassword | Error::Unauthorized => StatusCode::UNAUTHORIZED,
+            Error::NotOwner | Error::NotAdmin | Error::ReservedEmail => StatusCode::FORBIDDEN,
+            Error::VersionMismatch => StatusCode::PRECONDITION_FAILED,
+            Error::Offensive(_) | Error::InvalidImport(_) | Error::Validation(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            Error::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            Error::Hashing(e) => {
+                tracing::error!(%request_id, "Hashing error: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            #[cfg(feature = "postgres")]
+            Error::DatabaseQuery(e) => {
+                tracing::error!(%request_id, "Database error: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        (error.to_string(), status)
+    } else if let Some(error) = r.find::<MissingHeader>() {
+        (error.to_string(), StatusCode::UNAUTHORIZED)
+    } else if let Some(error) = r.find::<CorsForbidden>() {
+        (error.to_string(), StatusCode::FORBIDDEN)
+    } else if let Some(error) = r.find::<BodyDeserializeError>() {
+        (error.to_string(), StatusCode::UNPROCESSABLE_ENTITY)
+    } else {
+        ("Route not found".to_string(), StatusCode::NOT_FOUND)
+    };
+
+    let mut response =
+        warp::reply::with_status(format!("{} (request id: {})", message, request_id), status)
+            .into_response();
+    if let Some(Error::RateLimited(wait)) = r.find::<Error>() {
+        let seconds = wait.as_secs_f64().ceil().max(1.0) as u64;
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+    }
+    response
+}
+
+#[utoipa::path(
+    get,
+    path = "/questions",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size, default 20, at most 100"),
+        ("offset" = Option<usize>, Query, description = "Number of questions to skip"),
+        ("tag" = Option<String>, Query, description = "Only list questions with this tag"),
+        ("sort" = Option<String>, Query, description = "`id` (default), `score`, `newest`, `created_at` or `updated_at`"),
+        ("order" = Option<String>, Query, description = "`asc` or `desc`; score and newest default to desc, the rest to asc"),
+        ("include_deleted" = Option<bool>, Query, description = "Also list deleted questions, admins only"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached listing")
+    ),
+    responses(
+        (status = 200, description = "A page of questions", body = Page<Question>),
+        (status = 304, description = "The cached listing is still current"),
+        (status = 400, description = "Malformed pagination, sort, order or include_deleted parameter"),
+        (status = 401, description = "include_deleted without a valid token"),
+        (status = 403, description = "include_deleted by an account that is not an admin")
+    ),
+    tag = "questions"
+)]
+async fn get_questions(
+    params: HashMap<String, String>,
+    if_none_match: Option<String>,
+    session: Option<Session>,
+    admins: Admins,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    let pagination = extract_pagination(&params)?;
+    let include_deleted = match params.get("include_deleted").map(String::as_str) {
+        None | Some("false") => false,
+        Some("true") => {
+            let session = session.ok_or(Error::Unauthorized)?;
+            admins.check(&store, &session).await?;
+            true
+        }
+        Some(_) => {
+            return Err(warp::reject::custom(Error::InvalidParameter(
+                "include_deleted",
+            )))
+        }
+    };
+    let mut res: Vec<Question> = match params.get("tag") {
+        Some(tag) => {
+            let ids = store
+                .tags
+                .read()
+                .await
+                .get(&tag.to_lowercase())
+                .cloned()
+                .unwrap_or_default();
+            let questions = store.questions.read().await;
+            ids.iter()
+                .filter_map(|id| questions.get(id).cloned())
+                .collect()
+        }
+        None => store.questions.read().await.values().cloned().collect(),
+    };
+    if !include_deleted {
+        res.retain(|q| q.deleted_at.is_none());
+    }
+
+    // Each key has a natural direction that `order` can override; ties
+    // always fall back to the id so pages stay stable.
+    type Key = fn(&Question, &Question) -> Ordering;
+    let (key, descending): (Key, bool) = match params.get("sort").map(String::as_str) {
+        None | Some("id") => (|a, b| a.id.0.cmp(&b.id.0), false),
+        Some("score") => (|a, b| a.score.cmp(&b.score), true),
+        Some("newest") => (|a, b| a.created_at.cmp(&b.created_at), true),
+        Some("created_at") => (|a, b| a.created_at.cmp(&b.created_at), false),
+        Some("updated_at") => (|a, b| a.updated_at.cmp(&b.updated_at), false),
+        Some(_) => return Err(warp::reject::custom(Error::InvalidParameter("sort"))),
+    };
+    let descending = match params.get("order").map(String::as_str) {
+        None => descending,
+        Some("asc") => false,
+        Some("desc") => true,
+        Some(_) => return Err(warp::reject::custom(Error::InvalidParameter("order"))),
+    };
+    res.sort_by(|a, b| {
+        let ordering = key(a, b);
+        let ordering = if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+        ordering.then_with(|| a.id.0.cmp(&b.id.0))
+    });
+    Ok(json_with_etag(&paginate(res, &pagination), if_none_match))
+}
+
+#[utoipa::path(
+    get,
+    path = "/questions/{id}",
+    params(("id" = String, Path, description = "Question id")),
+    responses(
+        (status = 200, description = "The question, with its version as the ETag", body = Question),
+        (status = 404, description = "Question not found")
+    ),
+    tag = "questions"
+)]
+async fn get_question(id: String, store: Store) -> Result<impl Reply, Rejection> {
+    match store
+        .questions
+        .read()
+        .await
+        .get(&QuestionId(id))
+        .filter(|q| q.deleted_at.is_none())
+    {
+        Some(question) => Ok(with_version(question)),
+        None => Err(warp::reject::custom(Error::QuestionNotFound)),
+    }
+}
+
+fn with_version(question: &Question) -> Response {
+    warp::reply::with_header(
+        warp::reply::json(question),
+        header::ETAG,
+        format!("\"{}\"", question.version),
+    )
+    .into_response()
+}
+
+// If-Match holds one or more quoted versions, or `*` for any. It uses
+// strong comparison, so weak tags never match; the gzip variant of a
+// version's tag names that same version.
+fn check_version(if_match: Option<&str>, question: &Question) -> Result<(), Error> {
+    let Some(if_match) = if_match else {
+        return Ok(());
+    };
+    let current = format!("\"{}\"", question.version);
+    let matches = if_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || compression::identity_etag(tag) == current);
+    if matches {
+        Ok(())
+    } else {
+        Err(Error::VersionMismatch)
+    }
+}
+
+fn search_score(question: &Question, terms: &[String]) -> usize {
+    let title = question.title.to_lowercase();
+    let content = question.content.to_lowercase();
+    let tags: Vec<String> = question
+        .tags
+        .iter()
+        .flatten()
+        .map(|tag| tag.to_lowercase())
+        .collect();
+
+    terms
+        .iter()
+        .map(|term| {
+            title.matches(term.as_str()).count()
+                + content.matches(term.as_str()).count()
+                + tags
+                    .iter()
+                    .filter(|tag| tag.contains(term.as_str()))
+                    .count()
+        })
+        .sum()
+}
+
+#[utoipa::path(
+    get,
+    path = "/questions/search",
+    params(
+        ("q" = String, Query, description = "Whitespace-separated search terms"),
+        ("limit" = Option<usize>, Query, description = "Page size, default 20, at most 100"),
+        ("offset" = Option<usize>, Query, description = "Number of results to skip")
+    ),
+    responses(
+        (status = 200, description = "Matching questions, best match first", body = Page<Question>),
+        (status = 400, description = "Missing query or malformed pagination")
+    ),
+    tag = "questions"
+)]
+async fn search_questions(
+    params: HashMap<String, String>,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    let terms: Vec<String> = match params.get("q") {
+        Some(q) => q.split_whitespace().map(|t| t.to_lowercase()).collect(),
+        None => return Err(warp::reject::custom(Error::MissingParameters)),
+    };
+
+    let mut ranked: Vec<(usize, Question)> = store
+        .questions
+        .read()
+        .await
+        .values()
+        .filter(|q| q.deleted_at.is_none())
+        .map(|q| (search_score(q, &terms), q.clone()))
+        .filter(|(score, _)| *score > 0)
+        .collect();
+    ranked.sort_by(|(a_score, a), (b_score, b)| b_score.cmp(a_score).then(a.id.0.cmp(&b.id.0)));
+
+    let pagination = extract_pagination(&params)?;
+    let res: Vec<Question> = ranked.into_iter().map(|(_, q)| q).collect();
+    Ok(warp::reply::json(&paginate(res, &pagination)))
+}
+
+async fn moderate_question(moderator: &Moderator, question: &Question) -> Result<(), Error> {
+    let mut texts = vec![question.title.as_str(), question.content.as_str()];
+    texts.extend(question.tags.iter().flatten().map(String::as_str));
+    ensure_clean(moderator.as_ref(), &texts).await
+}
+
+// Soft-deleted questions stay in the store but are gone as far as everything
+// except restore and the admin listing is concerned.
+fn live_question<'a>(
+    questions: &'a mut HashMap<QuestionId, Question>,
+    id: &QuestionId,
+) -> Result<&'a mut Question, Error> {
+    questions
+        .get_mut(id)
+        .filter(|q| q.deleted_at.is_none())
+        .ok_or(Error::QuestionNotFound)
+}
+
+fn check_owner(owner: &Option<AccountId>, session: &Session) -> Result<(), Error> {
+    match owner {
+        Some(account_id) if *account_id == session.account_id => Ok(()),
+        _ => Err(Error::NotOwner),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/questions",
+    params(("force" = Option<bool>, Query, description = "Create the question even if it looks like a duplicate")),
+    request_body = NewQuestion,
+    responses(
+        (status = 201, description = "The created question, also linked from Location", body = Question),
+        (status = 400, description = "Malformed force parameter"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 409, description = "Likely duplicates of the question", body = Duplicates),
+        (status = 422, description = "Invalid body or offensive content")
+    ),
+    security(("token" = [])),
+    tag = "questions"
+)]
+async fn add_question(
+    params: HashMap<String, String>,
+    session: Session,
+    store: Store,
+    moderator: Moderator,
+    new_question: NewQuestion,
+) -> Result<impl Reply, Rejection> {
+    let force = match params.get("force").map(String::as_str) {
+        None | Some("false") => false,
+        Some("true") => true,
+        Some(_) => return Err(warp::reject::custom(Error::InvalidParameter("force"))),
+    };
+    let now = Utc::now();
+    let question = Question {
+        account_id: Some(session.account_id),
+        created_at: Some(now),
+        updated_at: Some(now),
+        ..new_question.into_question(QuestionId(Uuid::new_v4().to_string()))
+    };
+    validate_question(&question)?;
+    moderate_question(&moderator, &question).await?;
+
+    let mut questions = store.questions.write().await;
+    if !force {
+        let duplicates = find_duplicates(&questions, &question.title);
+        if !duplicates.is_empty() {
+            let body = Duplicates {
+                message:
+                    "Likely duplicate of an existing question; pass force=true to post it anyway",
+                duplicates,
+            };
+            return Ok(
+                warp::reply::with_status(warp::reply::json(&body), StatusCode::CONFLICT)
+                    .into_response(),
+            );
+        }
+    }
+    store.save_question(&question).await?;
+    store.reindex_tags(None, Some(&question)).await;
+    store.reindex_owner_questions(None, Some(&question)).await;
+    questions.insert(question.id.clone(), question.clone());
+
+    let location = format!("/questions/{}", question.id.0);
+    Ok(warp::reply::with_header(
+        warp::reply::with_status(warp::reply::json(&question), StatusCode::CREATED),
+        header::LOCATION,
+        location,
+    )
+    .into_response())
+}
+
+// Live questions whose titles are at least DUPLICATE_THRESHOLD alike, most
+// similar first.
+fn find_duplicates(questions: &HashMap<QuestionId, Question>, title: &str) -> Vec<Duplicate> {
+    let mut duplicates: Vec<Duplicate> = questions
+        .values()
+        .filter(|q| q.deleted_at.is_none())
+        .map(|q| Duplicate {
+            id: q.id.clone(),
+            title: q.title.clone(),
+            similarity: similarity::similarity(title, &q.title),
+        })
+        .filter(|d| d.similarity >= DUPLICATE_THRESHOLD)
+        .collect();
+    duplicates.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| a.id.0.cmp(&b.id.0))
+    });
+    duplicates.truncate(DUPLICATES_MAX);
+    duplicates
+}
+
+#[utoipa::path(
+    put,
+    path = "/questions/{id}",
+    params(("id" = String, Path, description = "Question id")),
+    request_body = NewQuestion,
+    responses(
+        (status = 200, description = "Question updated"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "Not the owner of the question"),
+        (status = 404, description = "Question not found"),
+        (status = 422, description = "Invalid body or offensive content")
+    ),
+    security(("token" = [])),
+    tag = "questions"
+)]
+async fn update_question(
+    id: String,
+    session: Session,
+    store: Store,
+    moderator: Moderator,
+    updated: NewQuestion,
+) -> Result<impl Reply, Rejection> {
+    let id = QuestionId(id);
+    let question = updated.into_question(id.clone());
+    validate_question(&question)?;
+    moderate_question(&moderator, &question).await?;
+
+    // Only ever replaces an existing question; new ones go through POST.
+    let mut questions = store.questions.write().await;
+    let q = live_question(&mut questions, &id)?;
+    check_owner(&q.account_id, &session)?;
+    let question = Question {
+        account_id: q.account_id.clone(),
+        score: q.score,
+        version: q.version + 1,
+        created_at: q.created_at,
+        updated_at: Some(Utc::now()),
+        ..question
+    };
+    store.save_question(&question).await?;
+    store.reindex_tags(Some(q), Some(&question)).await;
+    *q = question;
+    Ok(warp::reply::with_status("Question updated", StatusCode::OK))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/questions/{id}",
+    params(
+        ("id" = String, Path, description = "Question id"),
+        ("If-Match" = Option<String>, Header, description = "Version the changes are based on")
+    ),
+    request_body = QuestionPatch,
+    responses(
+        (status = 200, description = "The updated question, with its new version as the ETag", body = Question),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "Not the owner of the question"),
+        (status = 404, description = "Question not found"),
+        (status = 412, description = "The question changed since the version in If-Match"),
+        (status = 422, description = "Invalid body or offensive content")
+    ),
+    security(("token" = [])),
+    tag = "questions"
+)]
+async fn patch_question(
+    id: String,
+    session: Session,
+    if_match: Option<String>,
+    store: Store,
+    moderator: Moderator,
+    patch: QuestionPatch,
+) -> Result<impl Reply, Rejection> {
+    // Only the new text needs moderating, and that can happen before the
+    // lock is taken.
+    let mut texts: Vec<&str> = patch
+        .title
+        .iter()
+        .chain(&patch.content)
+        .map(String::as_str)
+        .collect();
+    texts.extend(patch.tags.iter().flatten().map(String::as_str));
+    ensure_clean(moderator.as_ref(), &texts).await?;
+
+    let mut questions = store.questions.write().await;
+    let q = live_question(&mut questions, &QuestionId(id))?;
+    check_owner(&q.account_id, &session)?;
+    check_version(if_match.as_deref(), q)?;
+
+    let question = Question {
+        title: patch.title.unwrap_or_else(|| q.title.clone()),
+        content: patch.content.unwrap_or_else(|| q.content.clone()),
+        tags: patch.tags.or_else(|| q.tags.clone()),
+        version: q.version + 1,
+        updated_at: Some(Utc::now()),
+        ..q.clone()
+    };
+    validate_question(&question)?;
+    store.save_question(&question).await?;
+    store.reindex_tags(Some(q), Some(&question)).await;
+    *q = question;
+    Ok(with_version(q))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/questions/{id}",
+    params(("id" = String, Path, description = "Question id")),
+    responses(
+        (status = 200, description = "Question deleted"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "Not the owner of the question"),
+        (status = 404, description = "Question not found")
+    ),
+    security(("token" = [])),
+    tag = "questions"
+)]
+async fn delete_question(
+    id: String,
+    session: Session,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    let id = QuestionId(id);
+    let mut questions = store.questions.write().await;
+    let q = live_question(&mut questions, &id)?;
+    check_owner(&q.account_id, &session)?;
+
+    // Postgres keeps microseconds; rounding here keeps the timestamp equal
+    // after a reload so restore can still match the cascaded answers.
+    let now = Utc::now().trunc_subsecs(6);
+    store.soft_delete_question(&id, now).await?;
+    q.deleted_at = Some(now);
+    for answer in store.answers.write().await.values_mut() {
+        if answer.question_id == id && answer.deleted_at.is_none() {
+            answer.deleted_at = Some(now);
+        }
+    }
+    Ok(warp::reply::with_status("Question deleted", StatusCode::OK))
+}
+
+#[utoipa::path(
+    post,
+    path = "/questions/{id}/restore",
+    params(("id" = String, Path, description = "Question id")),
+    responses(
+        (status = 200, description = "Question and the answers deleted with it restored"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "Neither the owner of the question nor an admin"),
+        (status = 404, description = "Question not found")
+    ),
+    security(("token" = [])),
+    tag = "questions"
+)]
+async fn restore_question(
+    id: String,
+    session: Session,
+    admins: Admins,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    let id = QuestionId(id);
+    let mut questions = store.questions.write().await;
+    let q = questions.get_mut(&id).ok_or(Error::QuestionNotFound)?;
+    if check_owner(&q.account_id, &session).is_err() {
+        admins.check(&store, &session).await?;
+    }
+
+    // Only the answers removed together with the question come back;
+    // those share its deletion timestamp.
+    if let Some(deleted_at) = q.deleted_at {
+        store.restore_question(&id, deleted_at).await?;
+        q.deleted_at = None;
+        for answer in store.answers.write().await.values_mut() {
+            if answer.question_id == id && answer.deleted_at == Some(deleted_at) {
+                answer.deleted_at = None;
+            }
+        }
+    }
+    Ok(warp::reply::with_status(
+        "Question restored",
+        StatusCode::OK,
+    ))
+}
+
+async fn vote(
+    id: String,
+    value: i8,
+    session: Session,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    let id = QuestionId(id);
+    let mut questions = store.questions.write().await;
+    let q = live_question(&mut questions, &id)?;
+
+    let mut votes = store.votes.write().await;
+    let previous = votes
+        .get(&id)
+        .and_then(|v| v.get(&session.account_id))
+        .copied()
+        .unwrap_or(0);
+    if previous == value {
+        return Err(warp::reject::custom(Error::AlreadyVoted));
+    }
+
+    let question = Question {
+        score: q.score - i64::from(previous) + i64::from(value),
+        ..q.clone()
+    };
+    store
+        .save_vote(&question, &session.account_id, value)
+        .await?;
+    votes
+        .entry(id)
+        .or_default()
+        .insert(session.account_id, value);
+    *q = question;
+    Ok(warp::reply::json(q))
+}
+
+#[utoipa::path(
+    post,
+    path = "/questions/{id}/upvote",
+    params(("id" = String, Path, description = "Question id")),
+    responses(
+        (status = 200, description = "The question with its new score", body = Question),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Question not found"),
+        (status = 409, description = "Already upvoted")
+    ),
+    security(("token" = [])),
+    tag = "questions"
+)]
+async fn upvote(id: String, session: Session, store: Store) -> Result<impl Reply, Rejection> {
+    vote(id, 1, session, store).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/questions/{id}/downvote",
+    params(("id" = String, Path, description = "Question id")),
+    responses(
+        (status = 200, description = "The question with its new score", body = Question),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Question not found"),
+        (status = 409, description = "Already downvoted")
+    ),
+    security(("token" = [])),
+    tag = "questions"
+)]
+async fn downvote(id: String, session: Session, store: Store) -> Result<impl Reply, Rejection> {
+    vote(id, -1, session, store).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/tags",
+    responses((status = 200, description = "Every tag with its question count", body = [TagCount])),
+    tag = "tags"
+)]
+async fn get_tags(store: Store) -> Result<impl Reply, Rejection> {
+    let questions = store.questions.read().await;
+    let mut res: Vec<TagCount> = store
+        .tags
+        .read()
+        .await
+        .iter()
+        .map(|(tag, ids)| TagCount {
+            tag: tag.clone(),
+            count: ids
+                .iter()
+                .filter(|id| questions.get(id).is_some_and(|q| q.deleted_at.is_none()))
+                .count(),
+        })
+        .filter(|tag| tag.count > 0)
+        .collect();
+    res.sort_by(|a, b| b.count.cmp(&a.count).then(a.tag.cmp(&b.tag)));
+    Ok(warp::reply::json(&res))
+}
+
+#[utoipa::path(
+    post,
+    path = "/questions/{id}/tags",
+    params(("id" = String, Path, description = "Question id")),
+    request_body = Vec<String>,
+    responses(
+        (status = 200, description = "Tags added"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "Not the owner of the question"),
+        (status = 404, description = "Question not found"),
+        (status = 422, description = "Invalid or offensive tags")
+    ),
+    security(("token" = [])),
+    tag = "tags"
+)]
+async fn add_tags(
+    id: String,
+    session: Session,
+    store: Store,
+    moderator: Moderator,
+    new_tags: Vec<String>,
+) -> Result<impl Reply, Rejection> {
+    let texts: Vec<&str> = new_tags.iter().map(String::as_str).collect();
+    ensure_clean(moderator.as_ref(), &texts).await?;
+
+    let mut questions = store.questions.write().await;
+    let q = live_question(&mut questions, &QuestionId(id))?;
+    check_owner(&q.account_id, &session)?;
+    let mut question = q.clone();
+    let tags = question.tags.get_or_insert_with(Vec::new);
+    for tag in new_tags {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            tags.push(tag);
+        }
+    }
+    validate_tags(tags)?;
+    question.version += 1;
+    question.updated_at = Some(Utc::now());
+    store.save_question(&question).await?;
+    store.reindex_tags(Some(q), Some(&question)).await;
+    *q = question;
+    Ok(warp::reply::with_status("Tags added", StatusCode::OK))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/questions/{id}/tags/{tag}",
+    params(
+        ("id" = String, Path, description = "Question id"),
+        ("tag" = String, Path, description = "Tag to remove")
+    ),
+    responses(
+        (status = 200, description = "Tag removed"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "Not the owner of the question"),
+        (status = 404, description = "Question or tag not found")
+    ),
+    security(("token" = [])),
+    tag = "tags"
+)]
+async fn remove_tag(
+    id: String,
+    tag: String,
+    session: Session,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    let mut questions = store.questions.write().await;
+    let q = live_question(&mut questions, &QuestionId(id))?;
+    check_owner(&q.account_id, &session)?;
+    let mut question = q.clone();
+    let tags = question.tags.get_or_insert_with(Vec::new);
+    let before = tags.len();
+    tags.retain(|t| !t.eq_ignore_ascii_case(&tag));
+    if tags.len() == before {
+        return Err(warp::reject::custom(Error::TagNotFound));
+    }
+    question.version += 1;
+    question.updated_at = Some(Utc::now());
+    store.save_question(&question).await?;
+    store.reindex_tags(Some(q), Some(&question)).await;
+    *q = question;
+    Ok(warp::reply::with_status("Tag removed", StatusCode::OK))
+}
+
+#[utoipa::path(
+    post,
+    path = "/comments",
+    request_body(content(
+        (NewAnswer = "application/json"),
+        (NewAnswer = "application/x-www-form-urlencoded")
+    )),
+    responses(
+        (status = 200, description = "Answer added"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Question not found"),
+        (status = 422, description = "Invalid body or offensive content")
+    ),
+    security(("token" = [])),
+    tag = "answers"
+)]
+async fn add_answer(
+    session: Session,
+    store: Store,
+    moderator: Moderator,
+    new_answer: NewAnswer,
+) -> Result<impl Reply, Rejection> {
+    validate_answer(&new_answer.content)?;
+    ensure_clean(moderator.as_ref(), &[&new_answer.content]).await?;
+    if store
+        .questions
+        .read()
+        .await
+        .get(&new_answer.question_id)
+        .is_none_or(|q| q.deleted_at.is_some())
+    {
+        return Err(warp::reject::custom(Error::QuestionNotFound));
+    }
+
+    let now = Utc::now();
+    let answer = Answer {
+        id: AnswerId(Uuid::new_v4().to_string()),
+        content: new_answer.content,
+        question_id: new_answer.question_id,
+        account_id: Some(session.account_id),
+        created_at: Some(now),
+        updated_at: Some(now),
+        deleted_at: None,
+    };
+
+    let mut answers = store.answers.write().await;
+    store.save_answer(&answer).await?;
+    store.reindex_owner_answers(None, Some(&answer)).await;
+    answers.insert(answer.id.clone(), answer);
+    Ok(warp::reply::with_status("Answer added", StatusCode::OK))
+}
+
+#[utoipa::path(
+    get,
+    path = "/answers/{id}",
+    params(("id" = String, Path, description = "Answer id")),
+    responses(
+        (status = 200, description = "The answer", body = Answer),
+        (status = 404, description = "Answer not found")
+    ),
+    tag = "answers"
+)]
+async fn get_answer(id: String, store: Store) -> Result<impl Reply, Rejection> {
+    match store
+        .answers
+        .read()
+        .await
+        .get(&AnswerId(id))
+        .filter(|a| a.deleted_at.is_none())
+    {
+        Some(answer) => Ok(warp::reply::json(answer)),
+        None => Err(warp::reject::custom(Error::AnswerNotFound)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/answers/{id}",
+    params(("id" = String, Path, description = "Answer id")),
+    request_body = UpdatedAnswer,
+    responses(
+        (status = 200, description = "Answer updated"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "Not the owner of the answer"),
+        (status = 404, description = "Answer not found"),
+        (status = 422, description = "Invalid body or offensive content")
+    ),
+    security(("token" = [])),
+    tag = "answers"
+)]
+async fn update_answer(
+    id: String,
+    session: Session,
+    store: Store,
+    moderator: Moderator,
+    updated: UpdatedAnswer,
+) -> Result<impl Reply, Rejection> {
+    validate_answer(&updated.content)?;
+    ensure_clean(moderator.as_ref(), &[&updated.content]).await?;
+    match store
+        .answers
+        .write()
+        .await
+        .get_mut(&AnswerId(id))
+        .filter(|a| a.deleted_at.is_none())
+    {
+        Some(a) => {
+            check_owner(&a.account_id, &session)?;
+            let answer = Answer {
+                content: updated.content,
+                updated_at: Some(Utc::now()),
+                ..a.clone()
+            };
+            store.save_answer(&answer).await?;
+            *a = answer;
+            Ok(warp::reply::with_status("Answer updated", StatusCode::OK))
+        }
+        None => Err(warp::reject::custom(Error::AnswerNotFound)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/answers/{id}",
+    params(("id" = String, Path, description = "Answer id")),
+    responses(
+        (status = 200, description = "Answer deleted"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "Not the owner of the answer"),
+        (status = 404, description = "Answer not found")
+    ),
+    security(("token" = [])),
+    tag = "answers"
+)]
+async fn delete_answer(
+    id: String,
+    session: Session,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    let id = AnswerId(id);
+    let mut answers = store.answers.write().await;
+    match answers.get(&id).filter(|a| a.deleted_at.is_none()) {
+        Some(a) => check_owner(&a.account_id, &session)?,
+        None => return Err(warp::reject::custom(Error::AnswerNotFound)),
+    }
+    store.remove_answer(&id).await?;
+    store.reindex_owner_answers(answers.get(&id), None).await;
+    answers.remove(&id);
+    Ok(warp::reply::with_status("Answer deleted", StatusCode::OK))
+}
+
+#[utoipa::path(
+    get,
+    path = "/comments",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size, default 20, at most 100"),
+        ("cursor" = Option<String>, Query, description = "`next_cursor` of the previous page")
+    ),
+    responses(
+        (status = 200, description = "A page of answers, oldest first", body = CursorPage<Answer>),
+        (status = 400, description = "Malformed limit or cursor")
+    ),
+    tag = "answers"
+)]
+async fn get_all_comments(
+    params: HashMap<String, String>,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    let pagination = extract_cursor_pagination(&params)?;
+    let res: Vec<Answer> = store
+        .answers
+        .read()
+        .await
+        .values()
+        .filter(|answer| answer.deleted_at.is_none())
+        .cloned()
+        .collect();
+    Ok(warp::reply::json(&paginate_by_cursor(
+        res,
+        &pagination,
+        answer_cursor,
+    )))
+}
+
+fn answer_cursor(answer: &Answer) -> Cursor {
+    Cursor::new(answer.created_at, &answer.id.0)
+}
+
+#[utoipa::path(
+    get,
+    path = "/questions/{id}/comments",
+    params(
+        ("id" = String, Path, description = "Question id"),
+        ("limit" = Option<usize>, Query, description = "Page size, default 20, at most 100"),
+        ("cursor" = Option<String>, Query, description = "`next_cursor` of the previous page"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached listing")
+    ),
+    responses(
+        (status = 200, description = "A page of the question's answers, oldest first", body = CursorPage<Answer>),
+        (status = 304, description = "The cached listing is still current"),
+        (status = 400, description = "Malformed limit or cursor")
+    ),
+    tag = "answers"
+)]
+async fn get_comments_by_question_id(
+    id: String,
+    params: HashMap<String, String>,
+    if_none_match: Option<String>,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    let pagination = extract_cursor_pagination(&params)?;
+    let question_id = QuestionId(id);
+    let res: Vec<Answer> = store
+        .answers
+        .read()
+        .await
+        .values()
+        .filter(|answer| answer.question_id == question_id && answer.deleted_at.is_none())
+        .cloned()
+        .collect();
+    Ok(json_with_etag(
+        &paginate_by_cursor(res, &pagination, answer_cursor),
+        if_none_match,
+    ))
+}
+
+// Everything the routes need besides the store. The server builds it from
+// its `Config`; tests can fill it in directly.
+#[derive(Clone)]
+pub struct RouteOptions {
+    pub token_key: TokenKey,
+    pub admins: Admins,
+    pub moderator: Moderator,
+    pub cors_origins: Vec<String>,
+    pub rate_limit: f64,
+    pub rate_limit_burst: u32,
+    pub compression_threshold: usize,
+}
+
+impl RouteOptions {
+    // The token secret and the content filter come from the environment, as
+    // described on `TokenKey::from_env` and `moderation::from_env`.
+    pub fn from_config(config: &Config) -> Self {
+        RouteOptions {
+            token_key: TokenKey::from_env(),
+            admins: Admins::new(config.admin_emails.clone()),
+            moderator: moderation::from_env(),
+            cors_origins: config.cors_origins.clone(),
+            rate_limit: config.rate_limit,
+            rate_limit_burst: config.rate_limit_burst,
+            compression_threshold: config.compression_threshold,
+        }
+    }
+}
+
+// The whole API: every route, wrapped in CORS, rate limiting, compression,
+// request tracing and the mapping of rejections to error responses.
+pub fn build_routes(store: Store, options: RouteOptions) -> BoxedFilter<(impl Reply,)> {
+    let metrics = store.metrics.clone();
+    let store_filter = warp::any().map(move || store.clone());
+
+    let token_key = options.token_key;
+    let auth = auth::auth(token_key.clone());
+    let optional_auth = auth::optional_auth(token_key.clone());
+    let key_filter = warp::any().map(move || token_key.clone());
+
+    let admins = options.admins;
+    let admins_filter = warp::any().map(move || admins.clone());
+
+    let moderator = options.moderator;
+    let moderator_filter = warp::any().map(move || moderator.clone());
+
+    // Preflight requests are answered by the CORS wrapper itself, before any
+    // route is matched, so every path and method combination gets one.
+    let cors = warp::cors()
+        .allow_headers(vec![
+            "content-type",
+            "authorization",
+            "if-none-match",
+            "if-match",
+        ])
+        .expose_headers(vec!["etag", "location", "retry-after", "x-request-id"])
+        .allow_methods(&[
+            Method::GET,
+            Method::POST,
+            Method::DELETE,
+            Method::PUT,
+            Method::PATCH,
+        ]);
+    let cors = if options.cors_origins.iter().any(|origin| origin == "*") {
+        cors.allow_any_origin()
+    } else {
+        cors.allow_origins(options.cors_origins.iter().map(String::as_str))
+    };
+
+    let get_questions = warp::get()
+        .and(warp::path("questions"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(optional_auth)
+        .and(admins_filter.clone())
+        .and(store_filter.clone())
+        .and_then(get_questions);
+
+    let search_questions = warp::get()
+        .and(warp::path("questions"))
+        .and(warp::path("search"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(search_questions);
+
+    let get_question = warp::get()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and_then(get_question);
+
+    let update_question = warp::put()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and(moderator_filter.clone())
+        .and(warp::body::json())
+        .and_then(update_question);
+
+    let patch_question = warp::patch()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(auth.clone())
+        .and(warp::header::optional::<String>("if-match"))
+        .and(store_filter.clone())
+        .and(moderator_filter.clone())
+        .and(warp::body::json())
+        .and_then(patch_question);
+
+    let delete_question = warp::delete()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and_then(delete_question);
+
+    let add_question = warp::post()
+        .and(warp::path("questions"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and(moderator_filter.clone())
+        .and(warp::body::json())
+        .and_then(add_question);
+
+    let restore_question = warp::post()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("restore"))
+        .and(warp::path::end())
+        .and(auth.clone())
+        .and(admins_filter.clone())
+        .and(store_filter.clone())
+        .and_then(restore_question);
+
+    let upvote = warp::post()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("upvote"))
+        .and(warp::path::end())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and_then(upvote);
+
+    let downvote = warp::post()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("downvote"))
+        .and(warp::path::end())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and_then(downvote);
+
+    let get_tags = warp::get()
+        .and(warp::path("tags"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and_then(get_tags);
+
+    let add_tags = warp::post()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("tags"))
+        .and(warp::path::end())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and(moderator_filter.clone())
+        .and(warp::body::json())
+        .and_then(add_tags);
+
+    let remove_tag = warp::delete()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("tags"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and_then(remove_tag);
+
+    let add_answer = warp::post()
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and(moderator_filter.clone())
+        .and(warp::body::json().or(warp::body::form()).unify())
+        .and_then(add_answer);
+
+    let get_answer = warp::get()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and_then(get_answer);
+
+    let update_answer = warp::put()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and(moderator_filter.clone())
+        .and(warp::body::json())
+        .and_then(update_answer);
+
+    let delete_answer = warp::delete()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and_then(delete_answer);
+
+    let get_all_comments = warp::get()
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(get_all_comments);
+
+    let get_comments_by_question_id = warp::get()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(store_filter.clone())
+        .and_then(get_comments_by_question_id);
+
+    let question_html = warp::get()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("html"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and_then(markdown::question_html);
+
+    let registration = warp::post()
+        .and(warp::path("registration"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and(admins_filter.clone())
+        .and(warp::body::json())
+        .and_then(auth::register);
+
+    let login = warp::post()
+        .and(warp::path("login"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and(key_filter.clone())
+        .and(warp::body::json())
+        .and_then(auth::login);
+
+    let export = warp::get()
+        .and(warp::path("export"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(auth.clone())
+        .and(admins_filter.clone())
+        .and(store_filter.clone())
+        .and_then(transfer::export);
+
+    let import = warp::post()
+        .and(warp::path("import"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and(moderator_filter.clone())
+        .and(warp::header::optional::<String>("content-type"))
+        .and(warp::body::content_length_limit(IMPORT_LIMIT))
+        .and(warp::body::bytes())
+        .and_then(transfer::import);
+
+    let get_profile = warp::get()
+        .and(warp::path("accounts"))
+        .and(warp::path("me"))
+        .and(warp::path::end())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and_then(accounts::get_profile);
+
+    let update_profile = warp::put()
+        .and(warp::path("accounts"))
+        .and(warp::path("me"))
+        .and(warp::path::end())
+        .and(auth.clone())
+        .and(admins_filter.clone())
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(accounts::update_profile);
+
+    let my_questions = warp::get()
+        .and(warp::path("accounts"))
+        .and(warp::path("me"))
+        .and(warp::path("questions"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and_then(accounts::my_questions);
+
+    let my_answers = warp::get()
+        .and(warp::path("accounts"))
+        .and(warp::path("me"))
+        .and(warp::path("answers"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and_then(accounts::my_answers);
+
+    let health = warp::get()
+        .and(warp::path("health"))
+        .and(warp::path::end())
+        .and_then(health::health);
+
+    let ready = warp::get()
+        .and(warp::path("ready"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and_then(health::ready);
+
+    let metrics_route = warp::get()
+        .and(warp::path("metrics"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and_then(metrics::metrics);
+
+    let routes = get_questions
+        .or(search_questions)
+        .or(get_question)
+        .or(update_question)
+        .or(patch_question)
+        .or(add_question)
+        .or(add_answer)
+        .or(get_answer)
+        .or(update_answer)
+        .or(delete_answer)
+        .or(delete_question)
+        .or(restore_question)
+        .or(upvote)
+        .or(downvote)
+        .or(get_tags)
+        .or(add_tags)
+        .or(remove_tag)
+        .or(get_all_comments)
+        .or(get_comments_by_question_id)
+        .or(question_html)
+        .or(registration)
+        .or(login)
+        .or(get_profile)
+        .or(update_profile)
+        .or(my_questions)
+        .or(my_answers)
+        .or(health)
+        .or(ready)
+        .or(metrics_route)
+        .or(export)
+        .or(import)
+        .or(openapi::routes())
+        .with(cors);
+
+    let limiter = RateLimiter::new(options.rate_limit, options.rate_limit_burst);
+    let routes = rate_limit::rate_limit(limiter)
+        .and(routes)
+        .map(|reply| Ok(Reply::into_response(reply)))
+        .or_else(|r| async move { Ok::<_, Infallible>((Err(r),)) });
+
+    let threshold = options.compression_threshold;
+    trace::request_info()
+        .and(compression::accepts_gzip())
+        .and(routes)
+        .then(
+            move |info: trace::RequestInfo, gzip: bool, result: Result<Response, Rejection>| {
+                let metrics = metrics.clone();
+                async move {
+                    let response = match result {
+                        Ok(response) => response,
+                        Err(r) => return_error(r, &info.id),
+                    };
+                    let response = compression::gzip(response, gzip, threshold).await;
+                    trace::finish(info, &metrics, response)
+                }
+            },
+        )
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn question(value: serde_json::Value) -> Question {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn session(account_id: &str) -> Session {
+        serde_json::from_value(serde_json::json!({ "account_id": account_id, "exp": 0 })).unwrap()
+    }
+
+    async fn body(reply: impl Reply) -> serde_json::Value {
+        let bytes = warp::hyper::body::to_bytes(reply.into_response().into_body())
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    fn rejection<T>(result: Result<T, Rejection>) -> Rejection {
+        match result {
+            Ok(_) => panic!("expected a rejection"),
+            Err(rejection) => rejection,
+        }
+    }
+
+    fn admins() -> Admins {
+        Admins::new(Vec::new())
+    }
+
+    fn moderator() -> Moderator {
+        std::sync::Arc::new(moderation::Disabled)
+    }
+
+    fn terms(query: &str) -> Vec<String> {
+        query.split_whitespace().map(str::to_lowercase).collect()
+    }
+
+    #[test]
+    fn search_counts_every_occurrence_in_title_content_and_tags() {
+        let q = question(serde_json::json!({
+            "id": "1",
+            "title": "Rust borrow checker",
+            "content": "Why does the borrow checker reject this borrow?",
+            "tags": ["Rust", "borrowing"]
+        }));
+        // "borrow": once in the title, twice in the content, once in a tag.
+        assert_eq!(search_score(&q, &terms("borrow")), 4);
+        assert_eq!(search_score(&q, &terms("RUST borrow")), 6);
+        assert_eq!(search_score(&q, &terms("python")), 0);
+    }
+
+    #[tokio::test]
+    async fn tags_are_indexed_case_insensitively_and_only_the_owner_edits_them() {
+        let store = Store::new();
+        let owner = session("owner");
+        let id = || "T1".to_string();
+        let q = question(serde_json::json!({
+            "id": "T1", "title": "Async traits", "content": "How?",
+            "tags": ["rust"], "account_id": "owner"
+        }));
+        store.reindex_tags(None, Some(&q)).await;
+        store.questions.write().await.insert(q.id.clone(), q);
+
+        let tags = vec!["Async".to_string(), "RUST".to_string()];
+        add_tags(id(), owner.clone(), store.clone(), moderator(), tags)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.questions.read().await[&QuestionId(id())].tags,
+            Some(vec!["rust".to_string(), "Async".to_string()])
+        );
+        let listed = body(get_tags(store.clone()).await.unwrap()).await;
+        assert!(listed
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!({ "tag": "async", "count": 1 })));
+        let params = HashMap::from([("tag".to_string(), "ASYNC".to_string())]);
+        let page = body(
+            get_questions(params, None, None, admins(), store.clone())
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(page["total"], 1);
+
+        let result = add_tags(
+            id(),
+            session("other"),
+            store.clone(),
+            moderator(),
+            vec!["x".to_string()],
+        )
+        .await;
+        assert!(matches!(rejection(result).find(), Some(Error::NotOwner)));
+
+        remove_tag(id(), "async".to_string(), owner.clone(), store.clone())
+            .await
+            .unwrap();
+        let result = remove_tag(id(), "async".to_string(), owner, store.clone()).await;
+        assert!(matches!(rejection(result).find(), Some(Error::TagNotFound)));
+        assert!(!store.tags.read().await.contains_key("async"));
+    }
+
+    #[tokio::test]
+    async fn votes_once_per_account_and_sorts_by_score() {
+        let store = Store::new();
+        let q = question(serde_json::json!({
+            "id": "V1", "title": "Why is my iterator lazy?", "content": "It does nothing"
+        }));
+        store.questions.write().await.insert(q.id.clone(), q);
+        let voter = || session("voter");
+        let by_score = || {
+            HashMap::from([
+                ("sort".to_string(), "score".to_string()),
+                ("limit".to_string(), "100".to_string()),
+            ])
+        };
+
+        let voted = body(
+            vote("V1".to_string(), 1, voter(), store.clone())
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(voted["score"], 1);
+        let result = vote("V1".to_string(), 1, voter(), store.clone()).await;
+        assert!(matches!(
+            rejection(result).find(),
+            Some(Error::AlreadyVoted)
+        ));
+        let page = body(
+            get_questions(by_score(), None, None, admins(), store.clone())
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(page["items"][0]["id"], "V1");
+
+        // Switching sides takes back the earlier vote.
+        let voted = body(
+            vote("V1".to_string(), -1, voter(), store.clone())
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(voted["score"], -1);
+        let page = body(
+            get_questions(by_score(), None, None, admins(), store.clone())
+                .await
+                .unwrap(),
+        )
+        .await;
+        let items = page["items"].as_array().unwrap();
+        assert_eq!(items.last().unwrap()["id"], "V1");
+    }
+
+    #[test]
+    fn if_match_accepts_the_current_version_in_any_form() {
+        let q = question(serde_json::json!({
+            "id": "1", "title": "t", "content": "c", "version": 3
+        }));
+        for header in [
+            None,
+            Some("\"3\""),
+            Some("\"3-gzip\""),
+            Some("\"1\", \"3\""),
+            Some("*"),
+        ] {
+            assert!(check_version(header, &q).is_ok(), "{:?}", header);
+        }
+    }
+
+    #[test]
+    fn if_match_rejects_other_versions() {
+        let q = question(serde_json::json!({
+            "id": "1", "title": "t", "content": "c", "version": 3
+        }));
+        for header in ["\"2\"", "\"30\"", "W/\"3\"", "3", ""] {
+            assert!(
+                matches!(check_version(Some(header), &q), Err(Error::VersionMismatch)),
+                "{}",
+                header
+            );
+        }
+    }
+}
@@ -1,280 +1,96 @@
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, sync::Arc};
-use uuid::Uuid;
-use warp::{
-    filters::{body::BodyDeserializeError, cors::CorsForbidden},
-    http::Method,
-    http::StatusCode,
-    reject::Reject,
-    Filter, Rejection, Reply,
+use std::time::Duration;
+
+use clap::Parser;
+use many_routers::{
+    build_routes,
+    config::Config,
+    snapshot::{self, Snapshotter},
+    store::Store,
+    RouteOptions,
 };
+use tracing_subscriber::EnvFilter;
 
-use tokio::sync::RwLock;
-
-#[derive(Deserialize, Serialize, Debug, Clone)]
-struct Question {
-    id: QuestionId,
-    title: String,
-    content: String,
-    tags: Option<Vec<String>>,
-}
-
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
-struct QuestionId(String);
-
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
-struct AnswerId(String);
-
-#[derive(Deserialize, Serialize, Debug, Clone)]
-struct Answer {
-    id: AnswerId,
-    content: String,
-    question_id: QuestionId,
-}
-
-#[derive(Debug)]
-struct Pagination {
-    start: usize,
-    end: usize,
-}
-
-#[derive(Clone)]
-struct Store {
-    questions: Arc<RwLock<HashMap<QuestionId, Question>>>,
-    answers: Arc<RwLock<HashMap<AnswerId, Answer>>>,
-}
-
-impl Store {
-    fn new() -> Self {
-        Store {
-            questions: Arc::new(RwLock::new(Self::init())),
-            answers: Arc::new(RwLock::new(HashMap::new())),
+#[tokio::main]
+async fn main() {
+    let config = Config::parse();
+
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+            EnvFilter::new(format!("many_routers={},warp=warn", config.log_level))
+        }))
+        .init();
+
+    #[cfg(feature = "postgres")]
+    let store = match config.database_url {
+        Some(ref url) => Some(
+            Store::connect(url)
+                .await
+                .expect("Cannot connect to the database"),
+        ),
+        None => None,
+    };
+    #[cfg(not(feature = "postgres"))]
+    let store = {
+        if config.database_url.is_some() {
+            tracing::warn!("Built without the postgres feature, ignoring the database URL");
         }
-    }
-
-    fn init() -> HashMap<QuestionId, Question> {
-        let file = include_str!("../questions.json");
-        serde_json::from_str(file).expect("Cannot parse questions.json")
-    }
-}
-
-#[derive(Debug)]
-enum Error {
-    ParseError(std::num::ParseIntError),
-    MissingParameters,
-    QuestionNotFound,
-}
+        None
+    };
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match *self {
-            Error::ParseError(ref err) => write!(f, "Cannot parse parameter: {}", err),
-            Error::MissingParameters => write!(f, "Missing parameters"),
-            Error::QuestionNotFound => write!(f, "Question not found"),
+    // Without a database the store lives in a JSON snapshot instead.
+    let (store, snapshotter) = match store {
+        Some(store) => (store, None),
+        None => {
+            let store = snapshot::load(&config.snapshot_path)
+                .await
+                .expect("Cannot read the store snapshot")
+                .unwrap_or_else(Store::new);
+            let snapshotter = Snapshotter::new(store.clone(), config.snapshot_path.clone());
+            if config.snapshot_interval > 0 {
+                snapshotter.spawn_periodic(Duration::from_secs(config.snapshot_interval));
+            }
+            (store, Some(snapshotter))
         }
-    }
-}
-
-impl Reject for Error {}
-
-async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
-    if let Some(error) = r.find::<Error>() {
-        Ok(warp::reply::with_status(
-            error.to_string(),
-            StatusCode::RANGE_NOT_SATISFIABLE,
-        ))
-    } else if let Some(error) = r.find::<CorsForbidden>() {
-        Ok(warp::reply::with_status(
-            error.to_string(),
-            StatusCode::FORBIDDEN,
-        ))
-    } else if let Some(error) = r.find::<BodyDeserializeError>() {
-        Ok(warp::reply::with_status(
-            error.to_string(),
-            StatusCode::UNPROCESSABLE_ENTITY,
-        ))
-    } else {
-        Ok(warp::reply::with_status(
-            "Route not found".to_string(),
-            StatusCode::NOT_FOUND,
-        ))
-    }
-}
-
-fn extract_pagination(params: HashMap<String, String>) -> Result<Pagination, Error> {
-    if params.contains_key("start") && params.contains_key("end") {
-        return Ok(Pagination {
-            start: params
-                .get("start")
-                .unwrap()
-                .parse::<usize>()
-                .map_err(Error::ParseError)?,
-            end: params
-                .get("end")
-                .unwrap()
-                .parse::<usize>()
-                .map_err(Error::ParseError)?,
-        });
-    }
-    Err(Error::MissingParameters)
-}
-
-async fn get_questions(
-    params: HashMap<String, String>,
-    store: Store,
-) -> Result<impl Reply, Rejection> {
-    if !params.is_empty() {
-        let pagination = extract_pagination(params)?;
-        let res: Vec<Question> = store.questions.read().await.values().cloned().collect();
-        let res = &res[pagination.start..pagination.end];
-        Ok(warp::reply::json(&res))
-    } else {
-        let res: Vec<Question> = store.questions.read().await.values().cloned().collect();
-        Ok(warp::reply::json(&res))
-    }
-}
-
-async fn add_question(
-    store: Store,
-    question: Question,
-) -> Result<impl Reply, Rejection> {
-    store
-        .questions
-        .write()
-        .await
-        .insert(question.id.clone(), question);
-    Ok(warp::reply::with_status("Question added", StatusCode::OK))
-}
-
-async fn update_question(
-    id: String,
-    store: Store,
-    question: Question,
-) -> Result<impl Reply, Rejection> {
-    match store.questions.write().await.get_mut(&QuestionId(id)) {
-        Some(q) => {
-            *q = question;
-            Ok(warp::reply::with_status("Question updated", StatusCode::OK))
+    };
+    let routes = build_routes(store, RouteOptions::from_config(&config));
+
+    let shutdown = async {
+        let ctrl_c = async {
+            tokio::signal::ctrl_c()
+                .await
+                .expect("Cannot listen for ctrl-c");
+        };
+        #[cfg(unix)]
+        let terminate = async {
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .expect("Cannot listen for SIGTERM")
+                .recv()
+                .await;
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+        tokio::select! {
+            _ = ctrl_c => {}
+            _ = terminate => {}
+        }
+        tracing::info!("Shutting down, waiting for in-flight requests");
+    };
+    let (addr, server) = match warp::serve(routes)
+        .try_bind_with_graceful_shutdown((config.address, config.port), shutdown)
+    {
+        Ok(bound) => bound,
+        Err(e) => {
+            tracing::error!("Cannot bind to {}:{}: {}", config.address, config.port, e);
+            std::process::exit(1);
         }
-        None => Err(warp::reject::custom(Error::QuestionNotFound)),
-    }
-}
-
-async fn delete_question(id: String, store: Store) -> Result<impl Reply, Rejection> {
-    match store.questions.write().await.remove(&QuestionId(id)) {
-        Some(_) => Ok(warp::reply::with_status("Question deleted", StatusCode::OK)),
-        None => Err(warp::reject::custom(Error::QuestionNotFound)),
-    }
-}
-
-async fn add_answer(
-    store: Store,
-    params: HashMap<String, String>,
-) -> Result<impl Reply, Rejection> {
-    let answer = Answer {
-        id: AnswerId(Uuid::new_v4().to_string()),
-        content: params.get("content").unwrap().to_string(),
-        question_id: QuestionId(params.get("questionId").unwrap().to_string()),
     };
 
-    store
-        .answers
-        .write()
-        .await
-        .insert(answer.id.clone(), answer);
-    Ok(warp::reply::with_status("Answer added", StatusCode::OK))
-}
-
-async fn get_all_comments(store: Store) -> Result<impl Reply, Rejection> {
-    let res: Vec<Answer> = store.answers.read().await.values().cloned().collect();
-    Ok(warp::reply::json(&res))
-}
-
-async fn get_comments_by_question_id(
-    id: String,
-    store: Store,
-) -> Result<impl Reply, Rejection> {
-    let question_id = QuestionId(id);
-    let res: Vec<Answer> = store
-        .answers
-        .read()
-        .await
-        .values()
-        .cloned()
-        .filter(|answer| answer.question_id == question_id)
-        .collect();
-    Ok(warp::reply::json(&res))
-}
-
-#[tokio::main]
-async fn main() {
-    let store = Store::new();
-    let store_filter = warp::any().map(move || store.clone());
-
-    let cors = warp::cors()
-        .allow_any_origin()
-        .allow_header("content-type")
-        .allow_methods(&[Method::GET, Method::POST, Method::DELETE, Method::PUT]);
-
-    let get_questions = warp::get()
-        .and(warp::path("questions"))
-        .and(warp::path::end())
-        .and(warp::query())
-        .and(store_filter.clone())
-        .and_then(get_questions);
-
-    let update_question = warp::put()
-        .and(warp::path("questions"))
-        .and(warp::path::param::<String>())
-        .and(warp::path::end())
-        .and(store_filter.clone())
-        .and(warp::body::json())
-        .and_then(update_question);
-
-    let delete_question = warp::delete()
-        .and(warp::path("questions"))
-        .and(warp::path::param::<String>())
-        .and(warp::path::end())
-        .and(store_filter.clone())
-        .and_then(delete_question);
-
-    let add_question = warp::post()
-        .and(warp::path("questions"))
-        .and(warp::path::end())
-        .and(store_filter.clone())
-        .and(warp::body::json())
-        .and_then(add_question);
-
-    let add_answer = warp::post()
-        .and(warp::path("comments"))
-        .and(warp::path::end())
-        .and(store_filter.clone())
-        .and(warp::body::form())
-        .and_then(add_answer);
-
-    let get_all_comments = warp::get()
-        .and(warp::path("comments"))
-        .and(warp::path::end())
-        .and(store_filter.clone())
-        .and_then(get_all_comments);
-
-    let get_comments_by_question_id = warp::get()
-        .and(warp::path("questions"))
-        .and(warp::path::param::<String>())
-        .and(warp::path("comments"))
-        .and(warp::path::end())
-        .and(store_filter.clone())
-        .and_then(get_comments_by_question_id);
-
-    let routes = get_questions
-        .or(update_question)
-        .or(add_question)
-        .or(add_answer)
-        .or(delete_question)
-        .or(get_all_comments)
-        .or(get_comments_by_question_id)
-        .with(cors)
-        .recover(return_error);
+    tracing::info!("Listening on http://{}", addr);
+    server.await;
 
-    warp::serve(routes).run(([127, 0, 0, 1], 3030)).await;
+    if let Some(snapshotter) = snapshotter {
+        if let Err(e) = snapshotter.save().await {
+            tracing::error!("Cannot save store snapshot: {}", e);
+        }
+    }
 }
@@ -0,0 +1,106 @@
+use pulldown_cmark::{html, Options, Parser};
+use warp::{Rejection, Reply};
+
+use crate::{store::Store, Answer, Error, QuestionId};
+
+// Markdown is rendered with the common GitHub extensions, then run through
+// ammonia so raw HTML in the source can't inject scripts, styles or event
+// handlers into a client's page.
+fn render(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new_ext(markdown, options));
+    ammonia::clean(&unsafe_html)
+}
+
+// For plain text placed inside the generated markup, such as titles and ids.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[utoipa::path(
+    get,
+    path = "/questions/{id}/html",
+    params(("id" = String, Path, description = "Question id")),
+    responses(
+        (status = 200, description = "The question and its answers as an HTML fragment", body = String, content_type = "text/html"),
+        (status = 404, description = "No such question")
+    ),
+    tag = "questions"
+)]
+pub async fn question_html(id: String, store: Store) -> Result<impl Reply, Rejection> {
+    let id = QuestionId(id);
+    let questions = store.questions.read().await;
+    let question = questions
+        .get(&id)
+        .filter(|q| q.deleted_at.is_none())
+        .ok_or(Error::QuestionNotFound)?;
+
+    let stored = store.answers.read().await;
+    let mut answers: Vec<&Answer> = stored
+        .values()
+        .filter(|a| a.question_id == id && a.deleted_at.is_none())
+        .collect();
+    answers.sort_by(|a, b| (a.created_at, &a.id.0).cmp(&(b.created_at, &b.id.0)));
+
+    let mut page = format!(
+        "<article class=\"question\" id=\"question-{}\">\n<h1>{}</h1>\n{}</article>\n",
+        escape(&question.id.0),
+        escape(&question.title),
+        render(&question.content)
+    );
+    page.push_str("<section class=\"answers\">\n");
+    for answer in answers {
+        page.push_str(&format!(
+            "<article class=\"answer\" id=\"answer-{}\">\n{}</article>\n",
+            escape(&answer.id.0),
+            render(&answer.content)
+        ));
+    }
+    page.push_str("</section>\n");
+    Ok(warp::reply::html(page))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_github_flavoured_markdown() {
+        let html = render("# Title\n\n*em* ~~gone~~\n\n| a | b |\n|---|---|\n| 1 | 2 |\n");
+        assert!(html.contains("<h1>Title</h1>"), "{}", html);
+        assert!(html.contains("<em>em</em>"), "{}", html);
+        assert!(html.contains("<del>gone</del>"), "{}", html);
+        assert!(html.contains("<td>1</td>"), "{}", html);
+    }
+
+    #[test]
+    fn strips_scripts_and_event_handlers() {
+        let html = render("<script>alert(1)</script>\n\n<img src=x onerror=\"alert(2)\">\n\n[x](javascript:alert(3))");
+        assert!(!html.contains("<script"), "{}", html);
+        assert!(!html.contains("onerror"), "{}", html);
+        assert!(!html.contains("javascript:"), "{}", html);
+    }
+
+    #[test]
+    fn escapes_text_for_markup() {
+        assert_eq!(
+            escape("<a href=\"x\">Tom & Jerry's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+    }
+}
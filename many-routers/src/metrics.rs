@@ -0,0 +1,189 @@
+use std::{collections::BTreeMap, fmt::Write, sync::Mutex};
+
+use warp::{http::header, Rejection, Reply};
+
+use crate::store::Store;
+
+// Every route the API serves, with `{id}` and `{tag}` standing for any one
+// segment. Requests are labelled with the shape they match, or "other", so
+// the number of label values stays bounded whatever clients send.
+const ROUTES: &[&str] = &[
+    "/questions",
+    "/questions/search",
+    "/questions/{id}",
+    "/questions/{id}/comments",
+    "/questions/{id}/html",
+    "/questions/{id}/upvote",
+    "/questions/{id}/downvote",
+    "/questions/{id}/restore",
+    "/questions/{id}/tags",
+    "/questions/{id}/tags/{tag}",
+    "/comments",
+    "/answers/{id}",
+    "/tags",
+    "/registration",
+    "/login",
+    "/accounts/me",
+    "/accounts/me/questions",
+    "/accounts/me/answers",
+    "/export",
+    "/import",
+    "/api-doc.json",
+    "/health",
+    "/ready",
+    "/metrics",
+];
+
+const METHODS: &[&str] = &["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS"];
+
+fn matches(route: &str, path: &str) -> bool {
+    let mut route = route.split('/');
+    let mut path = path.trim_end_matches('/').split('/');
+    loop {
+        match (route.next(), path.next()) {
+            (None, None) => return true,
+            (Some(r), Some(p)) if r.starts_with('{') && !p.is_empty() => {}
+            (Some(r), Some(p)) if r == p => {}
+            _ => return false,
+        }
+    }
+}
+
+fn route_label(path: &str) -> &'static str {
+    // The Swagger UI serves its assets from anywhere below /docs.
+    if path == "/docs" || path.starts_with("/docs/") {
+        return "/docs";
+    }
+    ROUTES
+        .iter()
+        .find(|route| matches(route, path))
+        .copied()
+        .unwrap_or("other")
+}
+
+fn method_label(method: &str) -> &'static str {
+    METHODS
+        .iter()
+        .find(|known| **known == method)
+        .copied()
+        .unwrap_or("other")
+}
+
+// Request counters keyed by method, route and status, kept in a BTreeMap so
+// the exposition output is stable.
+#[derive(Default)]
+pub struct Metrics {
+    requests: Mutex<BTreeMap<(&'static str, &'static str, u16), u64>>,
+}
+
+impl Metrics {
+    pub fn record(&self, method: &str, path: &str, status: u16) {
+        let key = (method_label(method), route_label(path), status);
+        let mut requests = self.requests.lock().unwrap_or_else(|e| e.into_inner());
+        *requests.entry(key).or_default() += 1;
+    }
+
+    fn render_requests(&self, out: &mut String) {
+        let requests = self.requests.lock().unwrap_or_else(|e| e.into_inner());
+        out.push_str("# HELP http_requests_total Requests handled, by method, route and status.\n");
+        out.push_str("# TYPE http_requests_total counter\n");
+        for ((method, route, status), count) in requests.iter() {
+            let _ = writeln!(
+                out,
+                "http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                method, route, status, count
+            );
+        }
+    }
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: usize) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+// GET /metrics in the Prometheus text exposition format.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses((status = 200, description = "Prometheus metrics", content_type = "text/plain")),
+    tag = "operations"
+)]
+pub async fn metrics(store: Store) -> Result<impl Reply, Rejection> {
+    let questions = store
+        .questions
+        .read()
+        .await
+        .values()
+        .filter(|q| q.deleted_at.is_none())
+        .count();
+    let answers = store
+        .answers
+        .read()
+        .await
+        .values()
+        .filter(|a| a.deleted_at.is_none())
+        .count();
+    let accounts = store.accounts.read().await.len();
+
+    let mut out = String::new();
+    store.metrics.render_requests(&mut out);
+    gauge(
+        &mut out,
+        "qa_questions",
+        "Questions in the store, not counting deleted ones.",
+        questions,
+    );
+    gauge(
+        &mut out,
+        "qa_answers",
+        "Answers in the store, not counting deleted ones.",
+        answers,
+    );
+    gauge(&mut out, "qa_accounts", "Registered accounts.", accounts);
+
+    Ok(warp::reply::with_header(
+        out,
+        header::CONTENT_TYPE,
+        "text/plain; version=0.0.4",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_only_known_routes_and_methods() {
+        assert_eq!(route_label("/questions"), "/questions");
+        assert_eq!(route_label("/questions/"), "/questions");
+        assert_eq!(route_label("/questions/QI0001"), "/questions/{id}");
+        assert_eq!(
+            route_label("/questions/QI0001/html"),
+            "/questions/{id}/html"
+        );
+        assert_eq!(
+            route_label("/questions/QI0001/tags/rust"),
+            "/questions/{id}/tags/{tag}"
+        );
+        assert_eq!(route_label("/docs/swagger-ui.css"), "/docs");
+        assert_eq!(route_label("/questions/a/b/c/d"), "other");
+        assert_eq!(route_label("/questions//upvote"), "other");
+        assert_eq!(route_label("/nowhere"), "other");
+
+        assert_eq!(method_label("GET"), "GET");
+        assert_eq!(method_label("BREW"), "other");
+    }
+
+    #[test]
+    fn odd_requests_share_one_series() {
+        let metrics = Metrics::default();
+        for depth in 2..50 {
+            metrics.record("BREW", &"/questions/x".repeat(depth), 404);
+        }
+        let requests = metrics.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[&("other", "other", 404)], 48);
+    }
+}
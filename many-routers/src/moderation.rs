@@ -0,0 +1,152 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::Error;
+
+const BAD_WORDS_API_URL: &str = "https://api.apilayer.com/bad_words?censor_character=*";
+
+pub type Moderator = Arc<dyn ContentFilter>;
+
+#[async_trait]
+pub trait ContentFilter: Send + Sync {
+    // Returns the offending words found in `text`, empty when it is clean.
+    async fn check(&self, text: &str) -> Vec<String>;
+}
+
+// Picks the filter from the environment: MODERATION=off disables it,
+// BAD_WORDS_API_KEY enables the external API, otherwise the bundled word
+// list is used.
+pub fn from_env() -> Moderator {
+    if std::env::var("MODERATION").is_ok_and(|v| v == "off") {
+        return Arc::new(Disabled);
+    }
+    match std::env::var("BAD_WORDS_API_KEY") {
+        Ok(api_key) => Arc::new(BadWordsApi::new(api_key, WordList::bundled())),
+        Err(_) => Arc::new(WordList::bundled()),
+    }
+}
+
+pub async fn ensure_clean(filter: &dyn ContentFilter, texts: &[&str]) -> Result<(), Error> {
+    let mut offending: Vec<String> = Vec::new();
+    for text in texts {
+        for word in filter.check(text).await {
+            if !offending.contains(&word) {
+                offending.push(word);
+            }
+        }
+    }
+    if offending.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Offensive(offending))
+    }
+}
+
+pub struct Disabled;
+
+#[async_trait]
+impl ContentFilter for Disabled {
+    async fn check(&self, _text: &str) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+pub struct WordList {
+    words: HashSet<String>,
+}
+
+impl WordList {
+    pub fn new<I: IntoIterator<Item = String>>(words: I) -> Self {
+        WordList {
+            words: words.into_iter().map(|w| w.to_lowercase()).collect(),
+        }
+    }
+
+    pub fn bundled() -> Self {
+        let file = include_str!("../bad_words.txt");
+        Self::new(
+            file.lines()
+                .map(str::trim)
+                .filter(|w| !w.is_empty())
+                .map(String::from),
+        )
+    }
+}
+
+#[async_trait]
+impl ContentFilter for WordList {
+    async fn check(&self, text: &str) -> Vec<String> {
+        let mut found: Vec<String> = Vec::new();
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .map(str::to_lowercase)
+        {
+            if self.words.contains(&word) && !found.contains(&word) {
+                found.push(word);
+            }
+        }
+        found
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct BadWord {
+    word: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct BadWordsResponse {
+    bad_words_list: Vec<BadWord>,
+}
+
+// Client for the APILayer bad words API. When the API is unreachable or
+// answers with an error the local word list is used instead, so posting
+// never depends on a third party being up.
+pub struct BadWordsApi {
+    client: reqwest::Client,
+    api_key: String,
+    fallback: WordList,
+}
+
+impl BadWordsApi {
+    pub fn new(api_key: String, fallback: WordList) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(3))
+            .build()
+            .expect("Cannot build HTTP client");
+        BadWordsApi {
+            client,
+            api_key,
+            fallback,
+        }
+    }
+
+    async fn query(&self, text: &str) -> Result<Vec<String>, reqwest::Error> {
+        let res: BadWordsResponse = self
+            .client
+            .post(BAD_WORDS_API_URL)
+            .header("apikey", &self.api_key)
+            .body(text.to_string())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(res.bad_words_list.into_iter().map(|w| w.word).collect())
+    }
+}
+
+#[async_trait]
+impl ContentFilter for BadWordsApi {
+    async fn check(&self, text: &str) -> Vec<String> {
+        match self.query(text).await {
+            Ok(words) => words,
+            Err(e) => {
+                tracing::warn!("Bad words API unavailable, using local list: {}", e);
+                self.fallback.check(text).await
+            }
+        }
+    }
+}
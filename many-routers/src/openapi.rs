@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+use utoipa_swagger_ui::Config;
+use warp::{
+    http::{header, StatusCode, Uri},
+    path::{FullPath, Tail},
+    reply::Response,
+    Filter, Rejection, Reply,
+};
+
+use crate::{
+    accounts::{self, Profile, UpdatedProfile},
+    auth::{self, AccountId, Credentials},
+    health, metrics,
+    pagination::{CursorPage, Page},
+    transfer::{self, Dataset, ImportSummary},
+    Answer, AnswerId, Duplicate, Duplicates, NewAnswer, NewQuestion, Question, QuestionId,
+    QuestionPatch, TagCount, UpdatedAnswer,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "many-routers", description = "Question and answer API"),
+    paths(
+        crate::get_questions,
+        crate::search_questions,
+        crate::get_question,
+        crate::add_question,
+        crate::update_question,
+        crate::patch_question,
+        crate::delete_question,
+        crate::restore_question,
+        crate::upvote,
+        crate::downvote,
+        crate::get_tags,
+        crate::add_tags,
+        crate::remove_tag,
+        crate::add_answer,
+        crate::get_answer,
+        crate::update_answer,
+        crate::delete_answer,
+        crate::get_all_comments,
+        crate::get_comments_by_question_id,
+        crate::markdown::question_html,
+        auth::register,
+        auth::login,
+        accounts::get_profile,
+        accounts::update_profile,
+        accounts::my_questions,
+        accounts::my_answers,
+        health::health,
+        health::ready,
+        metrics::metrics,
+        transfer::export,
+        transfer::import,
+    ),
+    components(schemas(
+        Question,
+        QuestionId,
+        Answer,
+        AnswerId,
+        AccountId,
+        NewQuestion,
+        QuestionPatch,
+        NewAnswer,
+        UpdatedAnswer,
+        TagCount,
+        Duplicate,
+        Duplicates,
+        Credentials,
+        Page<Question>,
+        Page<Answer>,
+        CursorPage<Answer>,
+        Profile,
+        UpdatedProfile,
+        Dataset,
+        ImportSummary,
+    )),
+    modifiers(&TokenAuth)
+)]
+struct ApiDoc;
+
+struct TokenAuth;
+
+impl Modify for TokenAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "token",
+                SecurityScheme::Http(
+                    HttpBuilder::new()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .bearer_format("JWT")
+                        .build(),
+                ),
+            );
+        }
+    }
+}
+
+// GET /api-doc.json serves the spec and /docs the Swagger UI reading it.
+pub fn routes() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let spec = warp::get()
+        .and(warp::path("api-doc.json"))
+        .and(warp::path::end())
+        .map(|| warp::reply::json(&ApiDoc::openapi()).into_response());
+
+    let config = Arc::new(Config::from("/api-doc.json"));
+    let docs = warp::get()
+        .and(warp::path("docs"))
+        .and(warp::path::full())
+        .and(warp::path::tail())
+        .and(warp::any().map(move || config.clone()))
+        .and_then(serve_swagger);
+
+    spec.or(docs).unify()
+}
+
+async fn serve_swagger(
+    full_path: FullPath,
+    tail: Tail,
+    config: Arc<Config<'static>>,
+) -> Result<Response, Rejection> {
+    if full_path.as_str() == "/docs" {
+        return Ok(warp::redirect::found(Uri::from_static("/docs/")).into_response());
+    }
+
+    match utoipa_swagger_ui::serve(tail.as_str(), config) {
+        Ok(Some(file)) => {
+            let mut response = Response::new(file.bytes.into_owned().into());
+            if let Ok(value) = file.content_type.parse() {
+                response.headers_mut().insert(header::CONTENT_TYPE, value);
+            }
+            Ok(response)
+        }
+        Ok(None) => Err(warp::reject::not_found()),
+        Err(e) => {
+            tracing::error!("Cannot serve Swagger UI: {}", e);
+            Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
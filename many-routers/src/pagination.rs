@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::Error;
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Debug)]
+pub struct Pagination {
+    pub limit: usize,
+    pub offset: usize,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Pagination {
+            limit: DEFAULT_LIMIT,
+            offset: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+    pub next_offset: Option<usize>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    pub limit: usize,
+    pub next_cursor: Option<String>,
+}
+
+// Where a listing ordered by creation time left off. Ties on the timestamp
+// are broken by id, so every item has exactly one position even when
+// several were created in the same microsecond.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cursor {
+    micros: i64,
+    id: String,
+}
+
+impl Cursor {
+    pub fn new(created_at: Option<DateTime<Utc>>, id: &str) -> Self {
+        Cursor {
+            micros: created_at.map_or(i64::MIN, |at| at.timestamp_micros()),
+            id: id.to_string(),
+        }
+    }
+
+    // Clients are meant to pass the cursor back untouched, so its layout is
+    // hidden behind base64 and free to change.
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}:{}", self.micros, self.id))
+    }
+
+    fn decode(cursor: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let text = String::from_utf8(bytes).ok()?;
+        let (micros, id) = text.split_once(':')?;
+        Some(Cursor {
+            micros: micros.parse().ok()?,
+            id: id.to_string(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct CursorPagination {
+    pub limit: usize,
+    pub after: Option<Cursor>,
+}
+
+fn extract_limit(params: &HashMap<String, String>) -> Result<usize, Error> {
+    let limit = match params.get("limit") {
+        Some(limit) => limit.parse::<usize>().map_err(Error::Parse)?,
+        None => DEFAULT_LIMIT,
+    };
+    // A page of nothing would hand back the same offset or cursor forever.
+    if limit == 0 {
+        return Err(Error::InvalidParameter("limit"));
+    }
+    Ok(limit.min(MAX_LIMIT))
+}
+
+// Missing parameters fall back to the defaults; the limit is capped at
+// MAX_LIMIT so a single request can't dump the whole store.
+pub fn extract_pagination(params: &HashMap<String, String>) -> Result<Pagination, Error> {
+    let mut pagination = Pagination {
+        limit: extract_limit(params)?,
+        ..Pagination::default()
+    };
+    if let Some(offset) = params.get("offset") {
+        pagination.offset = offset.parse::<usize>().map_err(Error::Parse)?;
+    }
+    Ok(pagination)
+}
+
+pub fn extract_cursor_pagination(
+    params: &HashMap<String, String>,
+) -> Result<CursorPagination, Error> {
+    let after = match params.get("cursor") {
+        Some(cursor) => Some(Cursor::decode(cursor).ok_or(Error::InvalidParameter("cursor"))?),
+        None => None,
+    };
+    Ok(CursorPagination {
+        limit: extract_limit(params)?,
+        after,
+    })
+}
+
+pub fn paginate<T>(items: Vec<T>, pagination: &Pagination) -> Page<T> {
+    let total = items.len();
+    let offset = pagination.offset.min(total);
+    let end = offset.saturating_add(pagination.limit).min(total);
+    let items: Vec<T> = items.into_iter().skip(offset).take(end - offset).collect();
+
+    Page {
+        items,
+        total,
+        limit: pagination.limit,
+        offset,
+        next_offset: if end < total { Some(end) } else { None },
+    }
+}
+
+// Unlike `paginate`, the position comes from the last item seen rather than
+// a count, so items added or removed meanwhile don't shift the next page.
+pub fn paginate_by_cursor<T>(
+    items: Vec<T>,
+    pagination: &CursorPagination,
+    cursor: impl Fn(&T) -> Cursor,
+) -> CursorPage<T> {
+    let mut items: Vec<(Cursor, T)> = items
+        .into_iter()
+        .map(|item| (cursor(&item), item))
+        .filter(|(position, _)| {
+            pagination
+                .after
+                .as_ref()
+                .is_none_or(|after| position > after)
+        })
+        .collect();
+    items.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let next_cursor = if items.len() > pagination.limit {
+        items.truncate(pagination.limit);
+        items.last().map(|(position, _)| position.encode())
+    } else {
+        None
+    };
+    CursorPage {
+        items: items.into_iter().map(|(_, item)| item).collect(),
+        limit: pagination.limit,
+        next_cursor,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn defaults_apply_and_the_limit_is_capped() {
+        let pagination = extract_pagination(&params(&[])).unwrap();
+        assert_eq!((pagination.limit, pagination.offset), (DEFAULT_LIMIT, 0));
+
+        let pagination =
+            extract_pagination(&params(&[("limit", "1000"), ("offset", "5")])).unwrap();
+        assert_eq!((pagination.limit, pagination.offset), (MAX_LIMIT, 5));
+    }
+
+    #[test]
+    fn rejects_limits_and_offsets_that_are_not_numbers() {
+        for bad in [[("limit", "-1")], [("limit", "ten")], [("offset", "x")]] {
+            assert!(matches!(
+                extract_pagination(&params(&bad)),
+                Err(Error::Parse(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_a_zero_limit() {
+        assert!(matches!(
+            extract_pagination(&params(&[("limit", "0")])),
+            Err(Error::InvalidParameter("limit"))
+        ));
+        assert!(matches!(
+            extract_cursor_pagination(&params(&[("limit", "0")])),
+            Err(Error::InvalidParameter("limit"))
+        ));
+    }
+
+    #[test]
+    fn pages_through_the_items() {
+        let page = paginate(
+            (1..=5).collect(),
+            &Pagination {
+                limit: 2,
+                offset: 2,
+            },
+        );
+        assert_eq!(page.items, [3, 4]);
+        assert_eq!((page.total, page.offset, page.next_offset), (5, 2, Some(4)));
+
+        let last = paginate(
+            (1..=5).collect(),
+            &Pagination {
+                limit: 2,
+                offset: 4,
+            },
+        );
+        assert_eq!(last.items, [5]);
+        assert_eq!(last.next_offset, None);
+    }
+
+    #[test]
+    fn an_offset_past_the_end_gives_an_empty_page() {
+        let page = paginate(
+            vec![1, 2, 3],
+            &Pagination {
+                limit: usize::MAX,
+                offset: usize::MAX,
+            },
+        );
+        assert!(page.items.is_empty());
+        assert_eq!((page.offset, page.next_offset), (3, None));
+    }
+
+    fn cursor_params(limit: usize, after: Option<&Cursor>) -> CursorPagination {
+        CursorPagination {
+            limit,
+            after: after.cloned(),
+        }
+    }
+
+    #[test]
+    fn cursors_survive_a_round_trip_through_the_query() {
+        let at = DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap();
+        let cursor = Cursor::new(Some(at), "AI:1");
+        let encoded = cursor.encode();
+        let decoded = extract_cursor_pagination(&params(&[("cursor", &encoded)])).unwrap();
+        assert_eq!(decoded.after, Some(cursor));
+    }
+
+    #[test]
+    fn rejects_cursors_it_did_not_issue() {
+        for bad in ["not base64!", "bm9jb2xvbg", ""] {
+            assert!(
+                matches!(
+                    extract_cursor_pagination(&params(&[("cursor", bad)])),
+                    Err(Error::InvalidParameter("cursor"))
+                ),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn walks_the_items_in_order_without_repeats() {
+        // (created_at micros, id), with a tie on the timestamp.
+        let items = vec![(30, "c"), (10, "a"), (20, "b2"), (20, "b1"), (40, "d")];
+        let position =
+            |item: &(i64, &str)| Cursor::new(DateTime::from_timestamp_micros(item.0), item.1);
+
+        let mut seen = Vec::new();
+        let mut after = None;
+        loop {
+            let page =
+                paginate_by_cursor(items.clone(), &cursor_params(2, after.as_ref()), position);
+            seen.extend(page.items.iter().map(|item| item.1));
+            match page.next_cursor {
+                Some(next) => after = Cursor::decode(&next),
+                None => break,
+            }
+        }
+        assert_eq!(seen, ["a", "b1", "b2", "c", "d"]);
+    }
+
+    #[test]
+    fn items_added_before_the_cursor_do_not_shift_the_next_page() {
+        let position = |item: &i64| Cursor::new(DateTime::from_timestamp_micros(*item), "");
+        let first = paginate_by_cursor(vec![1, 2, 3, 4], &cursor_params(2, None), position);
+        let after = Cursor::decode(&first.next_cursor.unwrap());
+
+        let next = paginate_by_cursor(
+            vec![0, 1, 2, 3, 4],
+            &cursor_params(2, after.as_ref()),
+            position,
+        );
+        assert_eq!(next.items, [3, 4]);
+        assert_eq!(next.next_cursor, None);
+    }
+}
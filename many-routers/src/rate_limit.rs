@@ -0,0 +1,151 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use warp::{Filter, Rejection};
+
+use crate::Error;
+
+// Clients whose bucket has refilled are forgotten once every this many
+// requests, so the sweep never runs on each request under the lock.
+const PRUNE_EVERY: u32 = 1024;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Default)]
+struct Buckets {
+    clients: HashMap<IpAddr, Bucket>,
+    acquired_since_prune: u32,
+}
+
+// Token bucket per client IP: each client may burst up to `burst` requests
+// and then gets `rate` more per second.
+#[derive(Clone)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64, burst: u32) -> Self {
+        RateLimiter {
+            rate,
+            burst: f64::from(burst.max(1)),
+            buckets: Arc::new(Mutex::new(Buckets::default())),
+        }
+    }
+
+    // Takes one token for `ip`, or returns how long until one is available.
+    fn acquire(&self, ip: IpAddr) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        buckets.acquired_since_prune += 1;
+        if buckets.acquired_since_prune >= PRUNE_EVERY {
+            let (rate, burst) = (self.rate, self.burst);
+            buckets.clients.retain(|_, b| {
+                b.tokens + now.duration_since(b.updated).as_secs_f64() * rate < burst
+            });
+            buckets.acquired_since_prune = 0;
+        }
+
+        let bucket = buckets.clients.entry(ip).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+}
+
+// Rejects with Error::RateLimited once the client's bucket is empty. A rate
+// of zero disables the limiter; requests without a peer address pass.
+pub fn rate_limit(limiter: RateLimiter) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::addr::remote()
+        .and_then(move |addr: Option<SocketAddr>| {
+            let limiter = limiter.clone();
+            async move {
+                match addr {
+                    Some(addr) if limiter.rate > 0.0 => limiter
+                        .acquire(addr.ip())
+                        .map_err(|wait| warp::reject::custom(Error::RateLimited(wait))),
+                    _ => Ok(()),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(203, 0, 113, 7));
+
+    #[test]
+    fn allows_a_burst_then_says_how_long_to_wait() {
+        let limiter = RateLimiter::new(0.5, 3);
+        for _ in 0..3 {
+            assert_eq!(limiter.acquire(CLIENT), Ok(()));
+        }
+        let wait = limiter.acquire(CLIENT).unwrap_err();
+        assert!(wait > Duration::from_secs(1) && wait <= Duration::from_secs(2));
+    }
+
+    #[test]
+    fn each_client_has_its_own_bucket() {
+        let limiter = RateLimiter::new(0.001, 1);
+        assert_eq!(limiter.acquire(CLIENT), Ok(()));
+        assert!(limiter.acquire(CLIENT).is_err());
+        assert_eq!(limiter.acquire("198.51.100.1".parse().unwrap()), Ok(()));
+    }
+
+    #[test]
+    fn forgets_refilled_clients_every_so_often() {
+        let limiter = RateLimiter::new(1000.0, 1);
+        limiter.acquire("198.51.100.1".parse().unwrap()).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        for _ in 1..PRUNE_EVERY {
+            let _ = limiter.acquire(CLIENT);
+        }
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.clients.len(), 1);
+        assert!(buckets.clients.contains_key(&CLIENT));
+    }
+
+    #[tokio::test]
+    async fn the_filter_rejects_only_once_the_bucket_is_empty() {
+        let filter = rate_limit(RateLimiter::new(0.001, 1));
+        let request = || warp::test::request().remote_addr(SocketAddr::new(CLIENT, 50000));
+        assert!(request().filter(&filter).await.is_ok());
+        let rejection = request().filter(&filter).await.unwrap_err();
+        assert!(matches!(rejection.find(), Some(Error::RateLimited(_))));
+        // No peer address, as in tests and behind some proxies.
+        assert!(warp::test::request().filter(&filter).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn a_rate_of_zero_disables_the_filter() {
+        let filter = rate_limit(RateLimiter::new(0.0, 1));
+        for _ in 0..5 {
+            let request = warp::test::request().remote_addr(SocketAddr::new(CLIENT, 50000));
+            assert!(request.filter(&filter).await.is_ok());
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashSet;
+
+// Lowercases and reduces the text to words separated by single spaces, so
+// punctuation, case and spacing don't affect how alike two titles look.
+pub fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Every run of three characters in the normalized text, with a space of
+// padding at each end so short words still produce trigrams.
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    let chars: Vec<char> = format!(" {} ", normalize(text)).chars().collect();
+    chars.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+// Jaccard index of the two texts' trigrams: 1.0 for texts that normalize to
+// the same thing, 0.0 when they have nothing in common. Trigrams rather than
+// whole words keep typos and plurals from hiding a duplicate.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a = trigrams(a);
+    let b = trigrams(b);
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_drops_case_punctuation_and_spacing() {
+        assert_eq!(
+            normalize("  How do I   sort a Vec<T>?! "),
+            "how do i sort a vec t"
+        );
+    }
+
+    #[test]
+    fn normalize_keeps_non_ascii_letters() {
+        assert_eq!(normalize("Ünïcode—Straße"), "ünïcode straße");
+    }
+
+    #[test]
+    fn normalize_of_only_punctuation_is_empty() {
+        assert_eq!(normalize("?!... --"), "");
+    }
+
+    #[test]
+    fn identical_after_normalizing_is_one() {
+        assert_eq!(similarity("Sort a Vec?", "sort   a vec"), 1.0);
+    }
+
+    #[test]
+    fn unrelated_titles_score_low() {
+        assert!(similarity("How do I sort a vector", "Borrow checker and lifetimes") < 0.2);
+    }
+
+    #[test]
+    fn small_edits_score_high() {
+        assert!(similarity("How do I sort a vector?", "How to sort a vector") > 0.6);
+        assert!(similarity("Parsing JSON with serde", "Parsing json with serde.") > 0.9);
+    }
+
+    #[test]
+    fn similarity_is_symmetric() {
+        let a = "What is a trait object";
+        let b = "What are trait objects";
+        assert_eq!(similarity(a, b), similarity(b, a));
+    }
+
+    #[test]
+    fn empty_texts_are_not_similar() {
+        assert_eq!(similarity("", ""), 0.0);
+        assert_eq!(similarity("", "anything"), 0.0);
+    }
+}
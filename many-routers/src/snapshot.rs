@@ -0,0 +1,155 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    auth::{Account, AccountId},
+    store::Store,
+    Answer, Question, QuestionId,
+};
+
+#[derive(Deserialize, Serialize)]
+struct Vote {
+    question_id: QuestionId,
+    account_id: AccountId,
+    value: i8,
+}
+
+// Everything sorted by id, so an unchanged store serializes to the same
+// bytes and can be skipped.
+#[derive(Deserialize, Serialize)]
+struct Snapshot {
+    questions: Vec<Question>,
+    answers: Vec<Answer>,
+    #[serde(default)]
+    accounts: Vec<Account>,
+    #[serde(default)]
+    votes: Vec<Vote>,
+}
+
+impl Snapshot {
+    async fn take(store: &Store) -> Self {
+        let mut questions: Vec<Question> = store.questions.read().await.values().cloned().collect();
+        questions.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+        let mut answers: Vec<Answer> = store.answers.read().await.values().cloned().collect();
+        answers.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+        let mut accounts: Vec<Account> = store.accounts.read().await.values().cloned().collect();
+        accounts.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+        let mut votes: Vec<Vote> = store
+            .votes
+            .read()
+            .await
+            .iter()
+            .flat_map(|(question_id, votes)| {
+                votes.iter().map(|(account_id, value)| Vote {
+                    question_id: question_id.clone(),
+                    account_id: account_id.clone(),
+                    value: *value,
+                })
+            })
+            .collect();
+        votes.sort_by(|a, b| {
+            (&a.question_id.0, &a.account_id.0).cmp(&(&b.question_id.0, &b.account_id.0))
+        });
+        Snapshot {
+            questions,
+            answers,
+            accounts,
+            votes,
+        }
+    }
+
+    fn into_store(self) -> Store {
+        let mut votes: HashMap<QuestionId, HashMap<AccountId, i8>> = HashMap::new();
+        for vote in self.votes {
+            votes
+                .entry(vote.question_id)
+                .or_default()
+                .insert(vote.account_id, vote.value);
+        }
+        Store::from_parts(
+            self.questions
+                .into_iter()
+                .map(|q| (q.id.clone(), q))
+                .collect(),
+            self.answers
+                .into_iter()
+                .map(|a| (a.id.clone(), a))
+                .collect(),
+            self.accounts
+                .into_iter()
+                .map(|a| (a.id.clone(), a))
+                .collect(),
+            votes,
+        )
+    }
+}
+
+// Restores the store saved at `path`, or `None` when there is no snapshot
+// yet. A snapshot that exists but can't be read is an error rather than a
+// reason to start over from the bundled questions and overwrite it.
+pub async fn load(path: &Path) -> io::Result<Option<Store>> {
+    let bytes = match tokio::fs::read(path).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let snapshot: Snapshot = serde_json::from_slice(&bytes)?;
+    Ok(Some(snapshot.into_store()))
+}
+
+#[derive(Clone)]
+pub struct Snapshotter {
+    store: Store,
+    path: PathBuf,
+    // Bytes of the last snapshot written, to skip rewriting an idle store.
+    last: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Snapshotter {
+    pub fn new(store: Store, path: PathBuf) -> Self {
+        Snapshotter {
+            store,
+            path,
+            last: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    // Writes to a temporary file first and renames it over the snapshot, so
+    // a crash mid-write never leaves a truncated file behind.
+    pub async fn save(&self) -> io::Result<()> {
+        let bytes = serde_json::to_vec_pretty(&Snapshot::take(&self.store).await)?;
+        let mut last = self.last.lock().await;
+        if *last == bytes {
+            return Ok(());
+        }
+
+        let tmp = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, &bytes).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        *last = bytes;
+        tracing::debug!(path = %self.path.display(), "store snapshot saved");
+        Ok(())
+    }
+
+    pub fn spawn_periodic(&self, every: Duration) {
+        let snapshotter = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = snapshotter.save().await {
+                    tracing::error!("Cannot save store snapshot: {}", e);
+                }
+            }
+        });
+    }
+}
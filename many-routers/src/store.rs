@@ -0,0 +1,541 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+
+use crate::{
+    auth::{Account, AccountId},
+    metrics::Metrics,
+    Answer, AnswerId, Error, Question, QuestionId,
+};
+
+// The maps are the working set every handler reads from. When a database is
+// configured it is a write-through cache in front of Postgres: everything is
+// loaded once at startup and each mutation is written to the database before
+// the maps, so the data survives restarts. Reads never go back to Postgres,
+// so a second instance sharing the database would not see this one's writes,
+// and memory grows with the size of the database.
+#[derive(Clone)]
+pub struct Store {
+    pub questions: Arc<RwLock<HashMap<QuestionId, Question>>>,
+    pub answers: Arc<RwLock<HashMap<AnswerId, Answer>>>,
+    pub accounts: Arc<RwLock<HashMap<AccountId, Account>>>,
+    // Lowercased tag -> ids of the questions carrying it.
+    pub tags: Arc<RwLock<HashMap<String, HashSet<QuestionId>>>>,
+    // Question -> account -> +1 or -1, so each account votes at most once.
+    pub votes: Arc<RwLock<HashMap<QuestionId, HashMap<AccountId, i8>>>>,
+    // Account -> ids of the questions and answers it owns.
+    pub account_questions: Arc<RwLock<HashMap<AccountId, HashSet<QuestionId>>>>,
+    pub account_answers: Arc<RwLock<HashMap<AccountId, HashSet<AnswerId>>>>,
+    pub metrics: Arc<Metrics>,
+    #[cfg(feature = "postgres")]
+    db: Option<sqlx::PgPool>,
+}
+
+impl Default for Store {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Self::from_parts(Self::init(), HashMap::new(), HashMap::new(), HashMap::new())
+    }
+
+    // Builds the indices for data loaded from anywhere; the database, if
+    // any, is attached by the caller.
+    pub fn from_parts(
+        questions: HashMap<QuestionId, Question>,
+        answers: HashMap<AnswerId, Answer>,
+        accounts: HashMap<AccountId, Account>,
+        votes: HashMap<QuestionId, HashMap<AccountId, i8>>,
+    ) -> Self {
+        Store {
+            tags: Arc::new(RwLock::new(build_tag_index(&questions))),
+            account_questions: Arc::new(RwLock::new(build_owner_index(
+                questions.values().map(|q| (&q.account_id, &q.id)),
+            ))),
+            account_answers: Arc::new(RwLock::new(build_owner_index(
+                answers.values().map(|a| (&a.account_id, &a.id)),
+            ))),
+            questions: Arc::new(RwLock::new(questions)),
+            answers: Arc::new(RwLock::new(answers)),
+            accounts: Arc::new(RwLock::new(accounts)),
+            votes: Arc::new(RwLock::new(votes)),
+            metrics: Arc::new(Metrics::default()),
+            #[cfg(feature = "postgres")]
+            db: None,
+        }
+    }
+
+    fn init() -> HashMap<QuestionId, Question> {
+        let file = include_str!("../questions.json");
+        serde_json::from_str(file).expect("Cannot parse questions.json")
+    }
+
+    #[cfg(feature = "postgres")]
+    pub async fn connect(db_url: &str) -> Result<Self, sqlx::Error> {
+        let pool = db::connect(db_url).await?;
+
+        let mut questions = db::load_questions(&pool).await?;
+        if questions.is_empty() {
+            // Seed an empty database with the bundled questions.
+            questions = Self::init();
+            for question in questions.values() {
+                db::upsert_question(&pool, question).await?;
+            }
+        }
+        let answers = db::load_answers(&pool).await?;
+        let accounts = db::load_accounts(&pool).await?;
+        let votes = db::load_votes(&pool).await?;
+
+        Ok(Store {
+            db: Some(pool),
+            ..Self::from_parts(questions, answers, accounts, votes)
+        })
+    }
+
+    // Keeps the tag index in step with a question being added, replaced or
+    // removed. Callers hold the questions write lock, so the index is always
+    // locked second.
+    pub async fn reindex_tags(&self, old: Option<&Question>, new: Option<&Question>) {
+        let mut tags = self.tags.write().await;
+        if let Some(old) = old {
+            for tag in old.tags.iter().flatten() {
+                let key = tag.to_lowercase();
+                if let Some(ids) = tags.get_mut(&key) {
+                    ids.remove(&old.id);
+                    if ids.is_empty() {
+                        tags.remove(&key);
+                    }
+                }
+            }
+        }
+        if let Some(new) = new {
+            for tag in new.tags.iter().flatten() {
+                tags.entry(tag.to_lowercase())
+                    .or_default()
+                    .insert(new.id.clone());
+            }
+        }
+    }
+
+    // Takes every lock once, so a writer stuck while holding one shows up
+    // as a timeout, and round-trips to the database if there is one.
+    pub async fn ping(&self) -> Result<(), Error> {
+        drop(self.questions.read().await);
+        drop(self.answers.read().await);
+        drop(self.accounts.read().await);
+        #[cfg(feature = "postgres")]
+        if let Some(ref pool) = self.db {
+            db::ping(pool).await.map_err(Error::DatabaseQuery)?;
+        }
+        Ok(())
+    }
+
+    // Same contract as `reindex_tags`, for the per-account question index.
+    pub async fn reindex_owner_questions(&self, old: Option<&Question>, new: Option<&Question>) {
+        let mut index = self.account_questions.write().await;
+        reindex_owner(
+            &mut index,
+            old.map(|q| (&q.account_id, &q.id)),
+            new.map(|q| (&q.account_id, &q.id)),
+        );
+    }
+
+    // Callers hold the answers write lock.
+    pub async fn reindex_owner_answers(&self, old: Option<&Answer>, new: Option<&Answer>) {
+        let mut index = self.account_answers.write().await;
+        reindex_owner(
+            &mut index,
+            old.map(|a| (&a.account_id, &a.id)),
+            new.map(|a| (&a.account_id, &a.id)),
+        );
+    }
+
+    #[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+    pub async fn save_question(&self, question: &Question) -> Result<(), Error> {
+        #[cfg(feature = "postgres")]
+        if let Some(ref pool) = self.db {
+            db::upsert_question(pool, question)
+                .await
+                .map_err(Error::DatabaseQuery)?;
+        }
+        Ok(())
+    }
+
+    // Marks the question and its remaining answers deleted at `at`.
+    #[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+    pub async fn soft_delete_question(
+        &self,
+        id: &QuestionId,
+        at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "postgres")]
+        if let Some(ref pool) = self.db {
+            db::soft_delete_question(pool, id, at)
+                .await
+                .map_err(Error::DatabaseQuery)?;
+        }
+        Ok(())
+    }
+
+    // Undoes `soft_delete_question(id, deleted_at)`.
+    #[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+    pub async fn restore_question(
+        &self,
+        id: &QuestionId,
+        deleted_at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "postgres")]
+        if let Some(ref pool) = self.db {
+            db::restore_question(pool, id, deleted_at)
+                .await
+                .map_err(Error::DatabaseQuery)?;
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+    pub async fn save_answer(&self, answer: &Answer) -> Result<(), Error> {
+        #[cfg(feature = "postgres")]
+        if let Some(ref pool) = self.db {
+            db::upsert_answer(pool, answer)
+                .await
+                .map_err(Error::DatabaseQuery)?;
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+    pub async fn remove_answer(&self, id: &AnswerId) -> Result<(), Error> {
+        #[cfg(feature = "postgres")]
+        if let Some(ref pool) = self.db {
+            db::delete_answer(pool, id)
+                .await
+                .map_err(Error::DatabaseQuery)?;
+        }
+        Ok(())
+    }
+
+    // Records the vote and the question's new score together.
+    #[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+    pub async fn save_vote(
+        &self,
+        question: &Question,
+        account_id: &AccountId,
+        value: i8,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "postgres")]
+        if let Some(ref pool) = self.db {
+            db::upsert_vote(pool, question, account_id, value)
+                .await
+                .map_err(Error::DatabaseQuery)?;
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+    pub async fn save_account(&self, account: &Account) -> Result<(), Error> {
+        #[cfg(feature = "postgres")]
+        if let Some(ref pool) = self.db {
+            db::upsert_account(pool, account)
+                .await
+                .map_err(Error::DatabaseQuery)?;
+        }
+        Ok(())
+    }
+}
+
+type OwnerIndex<Id> = HashMap<AccountId, HashSet<Id>>;
+
+fn reindex_owner<Id: Clone + Eq + Hash>(
+    index: &mut OwnerIndex<Id>,
+    old: Option<(&Option<AccountId>, &Id)>,
+    new: Option<(&Option<AccountId>, &Id)>,
+) {
+    if let Some((Some(owner), id)) = old {
+        if let Some(ids) = index.get_mut(owner) {
+            ids.remove(id);
+            if ids.is_empty() {
+                index.remove(owner);
+            }
+        }
+    }
+    if let Some((Some(owner), id)) = new {
+        index.entry(owner.clone()).or_default().insert(id.clone());
+    }
+}
+
+fn build_owner_index<'a, Id: Clone + Eq + Hash + 'a>(
+    items: impl Iterator<Item = (&'a Option<AccountId>, &'a Id)>,
+) -> OwnerIndex<Id> {
+    let mut index = OwnerIndex::new();
+    for item in items {
+        reindex_owner(&mut index, None, Some(item));
+    }
+    index
+}
+
+fn build_tag_index(
+    questions: &HashMap<QuestionId, Question>,
+) -> HashMap<String, HashSet<QuestionId>> {
+    let mut index: HashMap<String, HashSet<QuestionId>> = HashMap::new();
+    for question in questions.values() {
+        for tag in question.tags.iter().flatten() {
+            index
+                .entry(tag.to_lowercase())
+                .or_default()
+                .insert(question.id.clone());
+        }
+    }
+    index
+}
+
+#[cfg(feature = "postgres")]
+mod db {
+    use std::collections::HashMap;
+
+    use chrono::{DateTime, Utc};
+    use sqlx::{
+        postgres::{PgPoolOptions, PgRow},
+        PgPool, Row,
+    };
+
+    use crate::{
+        auth::{Account, AccountId},
+        Answer, AnswerId, Question, QuestionId,
+    };
+
+    pub async fn connect(db_url: &str) -> Result<PgPool, sqlx::Error> {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(db_url)
+            .await?;
+        sqlx::migrate!("./migrations").run(&pool).await?;
+        Ok(pool)
+    }
+
+    pub async fn ping(pool: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query("SELECT 1").execute(pool).await?;
+        Ok(())
+    }
+
+    pub async fn load_questions(
+        pool: &PgPool,
+    ) -> Result<HashMap<QuestionId, Question>, sqlx::Error> {
+        let questions = sqlx::query(
+            "SELECT id, title, content, tags, account_id, score, version, created_at,
+                    updated_at, deleted_at
+             FROM questions",
+        )
+        .map(|row: PgRow| Question {
+            id: QuestionId(row.get("id")),
+            title: row.get("title"),
+            content: row.get("content"),
+            tags: row.get("tags"),
+            account_id: row.get::<Option<String>, _>("account_id").map(AccountId),
+            score: row.get("score"),
+            version: row.get("version"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+            deleted_at: row.get("deleted_at"),
+        })
+        .fetch_all(pool)
+        .await?;
+        Ok(questions.into_iter().map(|q| (q.id.clone(), q)).collect())
+    }
+
+    pub async fn load_answers(pool: &PgPool) -> Result<HashMap<AnswerId, Answer>, sqlx::Error> {
+        let answers = sqlx::query(
+            "SELECT id, content, question_id, account_id, created_at, updated_at, deleted_at
+             FROM answers",
+        )
+        .map(|row: PgRow| Answer {
+            id: AnswerId(row.get("id")),
+            content: row.get("content"),
+            question_id: QuestionId(row.get("question_id")),
+            account_id: row.get::<Option<String>, _>("account_id").map(AccountId),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+            deleted_at: row.get("deleted_at"),
+        })
+        .fetch_all(pool)
+        .await?;
+        Ok(answers.into_iter().map(|a| (a.id.clone(), a)).collect())
+    }
+
+    pub async fn upsert_question(pool: &PgPool, question: &Question) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO questions
+                 (id, title, content, tags, account_id, score, version, created_at,
+                  updated_at, deleted_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+             ON CONFLICT (id) DO UPDATE
+             SET title = EXCLUDED.title, content = EXCLUDED.content, tags = EXCLUDED.tags,
+                 account_id = EXCLUDED.account_id, score = EXCLUDED.score,
+                 version = EXCLUDED.version,
+                 created_at = EXCLUDED.created_at, updated_at = EXCLUDED.updated_at,
+                 deleted_at = EXCLUDED.deleted_at",
+        )
+        .bind(&question.id.0)
+        .bind(&question.title)
+        .bind(&question.content)
+        .bind(&question.tags)
+        .bind(question.account_id.as_ref().map(|a| &a.0))
+        .bind(question.score)
+        .bind(question.version)
+        .bind(question.created_at)
+        .bind(question.updated_at)
+        .bind(question.deleted_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn soft_delete_question(
+        pool: &PgPool,
+        id: &QuestionId,
+        at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query("UPDATE questions SET deleted_at = $2 WHERE id = $1")
+            .bind(&id.0)
+            .bind(at)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "UPDATE answers SET deleted_at = $2 WHERE question_id = $1 AND deleted_at IS NULL",
+        )
+        .bind(&id.0)
+        .bind(at)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await
+    }
+
+    pub async fn restore_question(
+        pool: &PgPool,
+        id: &QuestionId,
+        deleted_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query("UPDATE questions SET deleted_at = NULL WHERE id = $1")
+            .bind(&id.0)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "UPDATE answers SET deleted_at = NULL WHERE question_id = $1 AND deleted_at = $2",
+        )
+        .bind(&id.0)
+        .bind(deleted_at)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await
+    }
+
+    pub async fn load_votes(
+        pool: &PgPool,
+    ) -> Result<HashMap<QuestionId, HashMap<AccountId, i8>>, sqlx::Error> {
+        let rows = sqlx::query("SELECT question_id, account_id, value FROM votes")
+            .fetch_all(pool)
+            .await?;
+        let mut votes: HashMap<QuestionId, HashMap<AccountId, i8>> = HashMap::new();
+        for row in rows {
+            let value: i16 = row.get("value");
+            votes
+                .entry(QuestionId(row.get("question_id")))
+                .or_default()
+                .insert(AccountId(row.get("account_id")), value as i8);
+        }
+        Ok(votes)
+    }
+
+    pub async fn upsert_vote(
+        pool: &PgPool,
+        question: &Question,
+        account_id: &AccountId,
+        value: i8,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO votes (question_id, account_id, value) VALUES ($1, $2, $3)
+             ON CONFLICT (question_id, account_id) DO UPDATE SET value = EXCLUDED.value",
+        )
+        .bind(&question.id.0)
+        .bind(&account_id.0)
+        .bind(i16::from(value))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("UPDATE questions SET score = $2 WHERE id = $1")
+            .bind(&question.id.0)
+            .bind(question.score)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+
+    pub async fn upsert_answer(pool: &PgPool, answer: &Answer) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO answers
+                 (id, content, question_id, account_id, created_at, updated_at, deleted_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             ON CONFLICT (id) DO UPDATE
+             SET content = EXCLUDED.content, question_id = EXCLUDED.question_id,
+                 account_id = EXCLUDED.account_id, created_at = EXCLUDED.created_at,
+                 updated_at = EXCLUDED.updated_at, deleted_at = EXCLUDED.deleted_at",
+        )
+        .bind(&answer.id.0)
+        .bind(&answer.content)
+        .bind(&answer.question_id.0)
+        .bind(answer.account_id.as_ref().map(|a| &a.0))
+        .bind(answer.created_at)
+        .bind(answer.updated_at)
+        .bind(answer.deleted_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_answer(pool: &PgPool, id: &AnswerId) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM answers WHERE id = $1")
+            .bind(&id.0)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn load_accounts(pool: &PgPool) -> Result<HashMap<AccountId, Account>, sqlx::Error> {
+        let accounts = sqlx::query("SELECT id, email, password, display_name FROM accounts")
+            .map(|row: PgRow| Account {
+                id: AccountId(row.get("id")),
+                email: row.get("email"),
+                password: row.get("password"),
+                display_name: row.get("display_name"),
+            })
+            .fetch_all(pool)
+            .await?;
+        Ok(accounts.into_iter().map(|a| (a.id.clone(), a)).collect())
+    }
+
+    pub async fn upsert_account(pool: &PgPool, account: &Account) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO accounts (id, email, password, display_name) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (id) DO UPDATE
+             SET email = EXCLUDED.email, password = EXCLUDED.password,
+                 display_name = EXCLUDED.display_name",
+        )
+        .bind(&account.id.0)
+        .bind(&account.email)
+        .bind(&account.password)
+        .bind(&account.display_name)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use std::{convert::Infallible, fmt, time::Instant};
+
+use uuid::Uuid;
+use warp::{
+    http::{HeaderValue, Method},
+    path::FullPath,
+    reply::Response,
+    Filter,
+};
+
+use crate::metrics::Metrics;
+
+#[derive(Debug, Clone)]
+pub struct RequestId(String);
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct RequestInfo {
+    pub id: RequestId,
+    method: Method,
+    path: FullPath,
+    start: Instant,
+}
+
+// Assigns every incoming request a fresh UUID and remembers when it started.
+pub fn request_info() -> impl Filter<Extract = (RequestInfo,), Error = Infallible> + Clone {
+    warp::method()
+        .and(warp::path::full())
+        .map(|method, path| RequestInfo {
+            id: RequestId(Uuid::new_v4().to_string()),
+            method,
+            path,
+            start: Instant::now(),
+        })
+}
+
+// Tags the response with the request id, counts it and logs one line for
+// the call.
+pub fn finish(info: RequestInfo, metrics: &Metrics, mut response: Response) -> Response {
+    if let Ok(value) = HeaderValue::from_str(&info.id.0) {
+        response.headers_mut().insert("x-request-id", value);
+    }
+    metrics.record(
+        info.method.as_str(),
+        info.path.as_str(),
+        response.status().as_u16(),
+    );
+    tracing::info!(
+        request_id = %info.id,
+        method = %info.method,
+        path = info.path.as_str(),
+        status = response.status().as_u16(),
+        latency = ?info.start.elapsed(),
+        "request finished"
+    );
+    response
+}
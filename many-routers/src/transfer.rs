@@ -0,0 +1,422 @@
+use std::{
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use warp::{
+    http::{header, HeaderValue},
+    hyper::{body::Bytes, Body},
+    reply::Response,
+    Rejection, Reply,
+};
+
+use crate::{
+    auth::{Admins, Session},
+    check_owner, moderate_question,
+    moderation::{ensure_clean, Moderator},
+    store::Store,
+    validation::{validate_answer, validate_question},
+    Answer, Error, Question,
+};
+
+const NDJSON: &str = "application/x-ndjson";
+
+// The whole dataset, as produced by GET /export and accepted by POST /import.
+#[derive(Deserialize, Serialize, Debug, Default, ToSchema)]
+pub struct Dataset {
+    #[serde(default)]
+    questions: Vec<Question>,
+    #[serde(default)]
+    answers: Vec<Answer>,
+}
+
+// One line of the NDJSON form, tagged so questions and answers can share a
+// stream: {"type":"question",...} or {"type":"answer",...}.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Record {
+    Question(Question),
+    Answer(Answer),
+}
+
+#[derive(Serialize, Debug, Default, ToSchema)]
+pub struct ImportSummary {
+    questions_imported: usize,
+    answers_imported: usize,
+    skipped: usize,
+}
+
+#[derive(PartialEq)]
+enum Mode {
+    Skip,
+    Overwrite,
+}
+
+async fn snapshot(store: &Store) -> Dataset {
+    let mut questions: Vec<Question> = store.questions.read().await.values().cloned().collect();
+    questions.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+    let mut answers: Vec<Answer> = store.answers.read().await.values().cloned().collect();
+    answers.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+    Dataset { questions, answers }
+}
+
+#[utoipa::path(
+    get,
+    path = "/export",
+    params(("format" = Option<String>, Query, description = "`json` (default) or `ndjson`")),
+    responses(
+        (status = 200, description = "Every question and answer, deleted ones included", body = Dataset),
+        (status = 400, description = "Unknown format"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "Not an admin")
+    ),
+    security(("token" = [])),
+    tag = "transfer"
+)]
+pub async fn export(
+    params: HashMap<String, String>,
+    session: Session,
+    admins: Admins,
+    store: Store,
+) -> Result<impl Reply, Rejection> {
+    // A full dump includes soft-deleted records, which only admins may see.
+    admins.check(&store, &session).await?;
+    let ndjson = match params.get("format").map(String::as_str) {
+        None | Some("json") => false,
+        Some("ndjson") => true,
+        Some(_) => return Err(warp::reject::custom(Error::InvalidParameter("format"))),
+    };
+    let dataset = snapshot(&store).await;
+
+    if !ndjson {
+        return Ok(warp::reply::json(&dataset).into_response());
+    }
+
+    // Each record goes out as its own chunk, so large exports don't have to
+    // be assembled into one buffer first.
+    let records = dataset
+        .questions
+        .into_iter()
+        .map(Record::Question)
+        .chain(dataset.answers.into_iter().map(Record::Answer))
+        .map(|record| {
+            let mut line = serde_json::to_vec(&record).unwrap_or_default();
+            line.push(b'\n');
+            Ok::<_, Infallible>(line)
+        });
+    let mut response = Response::new(Body::wrap_stream(futures_util::stream::iter(records)));
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(NDJSON));
+    Ok(response)
+}
+
+fn parse(content_type: Option<&str>, body: &[u8]) -> Result<Dataset, Error> {
+    let is_ndjson = content_type.is_some_and(|t| t.starts_with(NDJSON));
+    if !is_ndjson {
+        return serde_json::from_slice(body).map_err(|e| Error::InvalidImport(e.to_string()));
+    }
+
+    let mut dataset = Dataset::default();
+    let text = std::str::from_utf8(body).map_err(|e| Error::InvalidImport(e.to_string()))?;
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(Record::Question(q)) => dataset.questions.push(q),
+            Ok(Record::Answer(a)) => dataset.answers.push(a),
+            Err(e) => return Err(Error::InvalidImport(format!("line {}: {}", n + 1, e))),
+        }
+    }
+    Ok(dataset)
+}
+
+// Rejects the whole payload before anything is written: ids must be present
+// and unique, and every answer must belong to a question that exists either
+// in the store or in the payload itself.
+fn validate(dataset: &Dataset, existing: &HashSet<&str>) -> Result<(), Error> {
+    let mut question_ids = HashSet::new();
+    for question in &dataset.questions {
+        if question.id.0.trim().is_empty() {
+            return Err(Error::InvalidImport(
+                "question with an empty id".to_string(),
+            ));
+        }
+        if !question_ids.insert(question.id.0.as_str()) {
+            return Err(Error::InvalidImport(format!(
+                "question {} appears twice",
+                question.id.0
+            )));
+        }
+    }
+
+    let mut answer_ids = HashSet::new();
+    for answer in &dataset.answers {
+        if answer.id.0.trim().is_empty() {
+            return Err(Error::InvalidImport("answer with an empty id".to_string()));
+        }
+        if !answer_ids.insert(answer.id.0.as_str()) {
+            return Err(Error::InvalidImport(format!(
+                "answer {} appears twice",
+                answer.id.0
+            )));
+        }
+        let question = answer.question_id.0.as_str();
+        if !question_ids.contains(question) && !existing.contains(question) {
+            return Err(Error::InvalidImport(format!(
+                "answer {} refers to unknown question {}",
+                answer.id.0, question
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/import",
+    params(("mode" = Option<String>, Query, description = "What to do with ids that already exist: `skip` (default) or `overwrite`")),
+    request_body(content(
+        (Dataset = "application/json"),
+        (String = "application/x-ndjson")
+    )),
+    responses(
+        (status = 200, description = "What was imported", body = ImportSummary),
+        (status = 400, description = "Unknown mode"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "Overwriting a record owned by someone else"),
+        (status = 422, description = "Malformed, inconsistent or offensive payload")
+    ),
+    security(("token" = [])),
+    tag = "transfer"
+)]
+pub async fn import(
+    params: HashMap<String, String>,
+    session: Session,
+    store: Store,
+    moderator: Moderator,
+    content_type: Option<String>,
+    body: Bytes,
+) -> Result<impl Reply, Rejection> {
+    let mode = match params.get("mode").map(String::as_str) {
+        None | Some("skip") => Mode::Skip,
+        Some("overwrite") => Mode::Overwrite,
+        Some(_) => return Err(warp::reject::custom(Error::InvalidParameter("mode"))),
+    };
+    let dataset = parse(content_type.as_deref(), &body)?;
+    for question in &dataset.questions {
+        validate_question(question)?;
+    }
+    for answer in &dataset.answers {
+        validate_answer(&answer.content)?;
+    }
+    for question in &dataset.questions {
+        moderate_question(&moderator, question).await?;
+    }
+    for answer in &dataset.answers {
+        ensure_clean(moderator.as_ref(), &[&answer.content]).await?;
+    }
+
+    let mut questions = store.questions.write().await;
+    let mut answers = store.answers.write().await;
+    validate(
+        &dataset,
+        &questions.keys().map(|id| id.0.as_str()).collect(),
+    )?;
+
+    // Ownership is checked up front too, so a 403 never leaves a partial
+    // import behind.
+    if mode == Mode::Overwrite {
+        for question in &dataset.questions {
+            if let Some(existing) = questions.get(&question.id) {
+                check_owner(&existing.account_id, &session)?;
+            }
+        }
+        for answer in &dataset.answers {
+            if let Some(existing) = answers.get(&answer.id) {
+                check_owner(&existing.account_id, &session)?;
+            }
+        }
+    }
+
+    // Imported records belong to whoever imports them, exactly as if they
+    // had been posted one by one.
+    let mut summary = ImportSummary::default();
+    let now = Utc::now();
+    for mut question in dataset.questions {
+        let existing = questions.get(&question.id);
+        if existing.is_some() && mode == Mode::Skip {
+            summary.skipped += 1;
+            continue;
+        }
+        question.account_id = Some(session.account_id.clone());
+        question.score = existing.map_or(0, |q| q.score);
+        question.version = existing.map_or(0, |q| q.version + 1);
+        question.created_at = question.created_at.or(Some(now));
+        question.updated_at = question.updated_at.or(question.created_at);
+        store.save_question(&question).await?;
+        store.reindex_tags(existing, Some(&question)).await;
+        store
+            .reindex_owner_questions(existing, Some(&question))
+            .await;
+        questions.insert(question.id.clone(), question);
+        summary.questions_imported += 1;
+    }
+    for mut answer in dataset.answers {
+        let existing = answers.get(&answer.id);
+        if existing.is_some() && mode == Mode::Skip {
+            summary.skipped += 1;
+            continue;
+        }
+        answer.account_id = Some(session.account_id.clone());
+        answer.created_at = answer.created_at.or(Some(now));
+        answer.updated_at = answer.updated_at.or(answer.created_at);
+        store.save_answer(&answer).await?;
+        store.reindex_owner_answers(existing, Some(&answer)).await;
+        answers.insert(answer.id.clone(), answer);
+        summary.answers_imported += 1;
+    }
+
+    tracing::info!(
+        questions = summary.questions_imported,
+        answers = summary.answers_imported,
+        skipped = summary.skipped,
+        "import finished"
+    );
+    Ok(warp::reply::json(&summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        auth::{Account, AccountId},
+        moderation::Disabled,
+    };
+
+    use super::*;
+
+    fn session(account_id: &str) -> Session {
+        serde_json::from_value(serde_json::json!({ "account_id": account_id, "exp": 0 })).unwrap()
+    }
+
+    async fn read(reply: impl Reply) -> Bytes {
+        warp::hyper::body::to_bytes(reply.into_response().into_body())
+            .await
+            .unwrap()
+    }
+
+    async fn json(reply: impl Reply) -> serde_json::Value {
+        serde_json::from_slice(&read(reply).await).unwrap()
+    }
+
+    async fn add_admin(store: &Store) -> Admins {
+        let admin = Account {
+            id: AccountId("admin".to_string()),
+            email: "admin@example.com".to_string(),
+            password: String::new(),
+            display_name: None,
+        };
+        store.accounts.write().await.insert(admin.id.clone(), admin);
+        Admins::new(vec!["admin@example.com".to_string()])
+    }
+
+    #[tokio::test]
+    async fn only_admins_can_export() {
+        let store = Store::new();
+        let admins = add_admin(&store).await;
+        let rejection = export(HashMap::new(), session("someone"), admins, store)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(rejection.find::<Error>(), Some(Error::NotAdmin)));
+    }
+
+    #[tokio::test]
+    async fn exports_round_trip_through_import() {
+        let source = Store::new();
+        let source_admins = add_admin(&source).await;
+        let dataset: Dataset = serde_json::from_value(serde_json::json!({
+            "questions": [{ "id": "R1", "title": "What does ? do?", "content": "With Option" }],
+            "answers": [{ "id": "A1", "content": "Returns early", "question_id": "R1" }]
+        }))
+        .unwrap();
+        for q in dataset.questions {
+            source.questions.write().await.insert(q.id.clone(), q);
+        }
+        for a in dataset.answers {
+            source.answers.write().await.insert(a.id.clone(), a);
+        }
+        let ndjson = HashMap::from([("format".to_string(), "ndjson".to_string())]);
+        let exported = read(
+            export(
+                ndjson,
+                session("admin"),
+                source_admins.clone(),
+                source.clone(),
+            )
+            .await
+            .unwrap(),
+        )
+        .await;
+        let before = json(
+            export(HashMap::new(), session("admin"), source_admins, source)
+                .await
+                .unwrap(),
+        )
+        .await;
+
+        let target = Store::new();
+        let target_admins = add_admin(&target).await;
+        target.questions.write().await.clear();
+        target.tags.write().await.clear();
+        let run_import = || {
+            import(
+                HashMap::new(),
+                session("importer"),
+                target.clone(),
+                Arc::new(Disabled),
+                Some(NDJSON.to_string()),
+                exported.clone(),
+            )
+        };
+        let summary = json(run_import().await.unwrap()).await;
+        let questions = before["questions"].as_array().unwrap().len();
+        assert_eq!(
+            summary,
+            serde_json::json!({ "questions_imported": questions, "answers_imported": 1, "skipped": 0 })
+        );
+
+        // Same records, now owned by the importer.
+        let after = json(
+            export(
+                HashMap::new(),
+                session("admin"),
+                target_admins,
+                target.clone(),
+            )
+            .await
+            .unwrap(),
+        )
+        .await;
+        for (old, new) in before["questions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .zip(after["questions"].as_array().unwrap())
+        {
+            assert_eq!((&old["id"], &old["title"]), (&new["id"], &new["title"]));
+            assert_eq!(new["account_id"], "importer");
+        }
+        assert_eq!(after["answers"][0]["content"], "Returns early");
+
+        // A second run finds everything already there.
+        let summary = json(run_import().await.unwrap()).await;
+        assert_eq!(summary["skipped"], questions + 1);
+    }
+}
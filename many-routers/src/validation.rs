@@ -0,0 +1,157 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::{Error, Question};
+
+const TITLE_MAX: usize = 200;
+const CONTENT_MAX: usize = 10_000;
+const ID_MAX: usize = 64;
+const TAGS_MAX: usize = 10;
+const TAG_MAX: usize = 32;
+const EMAIL_MAX: usize = 254;
+const DISPLAY_NAME_MAX: usize = 50;
+const PASSWORD_MIN: usize = 8;
+
+#[derive(Serialize, Debug)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.field, self.message)
+    }
+}
+
+// Collects every problem with a payload instead of stopping at the first, so
+// a client can fix them all in one round trip.
+#[derive(Default)]
+struct Errors(Vec<FieldError>);
+
+impl Errors {
+    fn check(&mut self, ok: bool, field: &'static str, message: impl Into<String>) {
+        if !ok {
+            self.0.push(FieldError {
+                field,
+                message: message.into(),
+            });
+        }
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Validation(self.0))
+        }
+    }
+}
+
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= ID_MAX
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn valid_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag.chars().count() <= TAG_MAX
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '+' | '#'))
+}
+
+fn check_email(errors: &mut Errors, email: &str) {
+    let valid_email = email.len() <= EMAIL_MAX
+        && email
+            .split_once('@')
+            .is_some_and(|(user, domain)| !user.is_empty() && !domain.is_empty());
+    errors.check(valid_email, "email", "must be an email address");
+}
+
+fn check_content(errors: &mut Errors, content: &str) {
+    errors.check(!content.trim().is_empty(), "content", "must not be empty");
+    errors.check(
+        content.chars().count() <= CONTENT_MAX,
+        "content",
+        format!("must be at most {} characters", CONTENT_MAX),
+    );
+}
+
+fn check_tags(errors: &mut Errors, tags: &[String]) {
+    errors.check(
+        tags.len() <= TAGS_MAX,
+        "tags",
+        format!("must contain at most {} tags", TAGS_MAX),
+    );
+    for tag in tags {
+        errors.check(
+            valid_tag(tag),
+            "tags",
+            format!(
+                "entry {:?} is not 1 to {} letters, digits or -_.+#",
+                tag, TAG_MAX
+            ),
+        );
+    }
+}
+
+pub fn validate_question(question: &Question) -> Result<(), Error> {
+    let mut errors = Errors::default();
+    errors.check(
+        valid_id(&question.id.0),
+        "id",
+        format!("must be 1 to {} letters, digits, '-' or '_'", ID_MAX),
+    );
+    let title = question.title.trim().chars().count();
+    errors.check(
+        (1..=TITLE_MAX).contains(&title),
+        "title",
+        format!("must be between 1 and {} characters", TITLE_MAX),
+    );
+    check_content(&mut errors, &question.content);
+    check_tags(&mut errors, question.tags.as_deref().unwrap_or_default());
+    errors.finish()
+}
+
+// Checks the tags a question would carry after POST /questions/{id}/tags.
+pub fn validate_tags(tags: &[String]) -> Result<(), Error> {
+    let mut errors = Errors::default();
+    check_tags(&mut errors, tags);
+    errors.finish()
+}
+
+pub fn validate_answer(content: &str) -> Result<(), Error> {
+    let mut errors = Errors::default();
+    check_content(&mut errors, content);
+    errors.finish()
+}
+
+pub fn validate_profile(email: &str, display_name: Option<&str>) -> Result<(), Error> {
+    let mut errors = Errors::default();
+    check_email(&mut errors, email);
+    if let Some(name) = display_name {
+        let length = name.trim().chars().count();
+        errors.check(
+            (1..=DISPLAY_NAME_MAX).contains(&length),
+            "display_name",
+            format!("must be between 1 and {} characters", DISPLAY_NAME_MAX),
+        );
+    }
+    errors.finish()
+}
+
+pub fn validate_credentials(email: &str, password: &str) -> Result<(), Error> {
+    let mut errors = Errors::default();
+    check_email(&mut errors, email);
+    errors.check(
+        password.chars().count() >= PASSWORD_MIN,
+        "password",
+        format!("must be at least {} characters", PASSWORD_MIN),
+    );
+    errors.finish()
+}
@@ -2,6 +2,7 @@
 
 BASE_URL="http://127.0.0.1:3030"
 SERVER_PID=""
+TOKEN=""
 
 function start_server() {
     if [ -z "$SERVER_PID" ]; then
@@ -28,16 +29,34 @@ function clean_server() {
     echo "服务器已清理"
 }
 
+function register() {
+    read -p "请输入邮箱: " email
+    read -p "请输入密码: " password
+
+    curl -X POST "$BASE_URL/registration" \
+    -H "Content-Type: application/json" \
+    -d "{\"email\": \"$email\", \"password\": \"$password\"}"
+}
+
+function login() {
+    read -p "请输入邮箱: " email
+    read -p "请输入密码: " password
+
+    TOKEN=$(curl -s -X POST "$BASE_URL/login" \
+    -H "Content-Type: application/json" \
+    -d "{\"email\": \"$email\", \"password\": \"$password\"}" | tr -d '"')
+    echo "令牌: $TOKEN"
+}
+
 function add_question() {
-    read -p "请输入问题ID: " id
     read -p "请输入问题标题: " title
     read -p "请输入问题内容: " content
     read -p "请输入问题标签(用逗号分隔): " tags
 
     curl -X POST "$BASE_URL/questions" \
+    -H "Authorization: Bearer $TOKEN" \
     -H "Content-Type: application/json" \
     -d "{
-          \"id\": \"$id\",
           \"title\": \"$title\",
           \"content\": \"$content\",
           \"tags\": [$(echo $tags | sed 's/,/\",\"/g' | sed 's/^/\"/' | sed 's/$/\"/')]
@@ -49,6 +68,7 @@ function add_answer() {
     read -p "请输入问题ID: " question_id
 
     curl -X POST "$BASE_URL/comments" \
+    -H "Authorization: Bearer $TOKEN" \
     -H "Content-Type: application/x-www-form-urlencoded" \
     -d "content=$content&questionId=$question_id"
 }
@@ -76,7 +96,9 @@ function show_menu() {
     echo "6) 获取所有问题"
     echo "7) 获取所有评论"
     echo "8) 获取特定问题的评论"
-    echo "9) 退出"
+    echo "9) 注册账户"
+    echo "10) 登录"
+    echo "11) 退出"
 }
 
 while true; do
@@ -108,6 +130,12 @@ while true; do
             get_comments_by_question_id
             ;;
         9)
+            register
+            ;;
+        10)
+            login
+            ;;
+        11)
             stop_server
             break
             ;;
@@ -0,0 +1,484 @@
+use std::sync::Arc;
+
+use many_routers::{
+    auth::{Admins, TokenKey},
+    build_routes,
+    moderation::WordList,
+    store::Store,
+    RouteOptions,
+};
+use serde_json::{json, Value};
+use warp::{
+    http::{Response, StatusCode},
+    hyper::body::Bytes,
+    test::request,
+    Filter, Reply,
+};
+
+fn options() -> RouteOptions {
+    RouteOptions {
+        token_key: TokenKey::from_env(),
+        admins: Admins::new(vec!["admin@example.com".to_string()]),
+        moderator: Arc::new(WordList::bundled()),
+        cors_origins: Vec::new(),
+        rate_limit: 0.0,
+        rate_limit_burst: 1,
+        compression_threshold: 1024,
+    }
+}
+
+fn body(response: &Response<Bytes>) -> Value {
+    serde_json::from_slice(response.body()).expect("response is not JSON")
+}
+
+fn text(response: &Response<Bytes>) -> String {
+    String::from_utf8_lossy(response.body()).into_owned()
+}
+
+async fn token<F>(api: &F, email: &str) -> String
+where
+    F: Filter + 'static,
+    F::Extract: Reply + Send,
+{
+    let credentials = json!({ "email": email, "password": "secret-password" });
+    let response = request()
+        .method("POST")
+        .path("/registration")
+        .json(&credentials)
+        .reply(api)
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = request()
+        .method("POST")
+        .path("/login")
+        .json(&credentials)
+        .reply(api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    format!("Bearer {}", body(&response).as_str().unwrap())
+}
+
+async fn add_question<F>(api: &F, token: &str, title: &str) -> Value
+where
+    F: Filter + 'static,
+    F::Extract: Reply + Send,
+{
+    let response = request()
+        .method("POST")
+        .path("/questions")
+        .header("authorization", token)
+        .json(&json!({ "title": title, "content": "Some content", "tags": ["rust"] }))
+        .reply(api)
+        .await;
+    assert_eq!(
+        response.status(),
+        StatusCode::CREATED,
+        "{}",
+        text(&response)
+    );
+    body(&response)
+}
+
+#[tokio::test]
+async fn lists_the_bundled_questions() {
+    let api = build_routes(Store::new(), options());
+    let response = request().path("/questions").reply(&api).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let page = body(&response);
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["items"][0]["id"], "QI0001");
+    assert!(response.headers().contains_key("etag"));
+    assert!(response.headers().contains_key("x-request-id"));
+}
+
+#[tokio::test]
+async fn creates_reads_updates_and_deletes_a_question() {
+    let api = build_routes(Store::new(), options());
+    let token = token(&api, "owner@example.com").await;
+
+    let question = add_question(&api, &token, "How do lifetimes work?").await;
+    let id = question["id"].as_str().unwrap();
+    let path = format!("/questions/{}", id);
+
+    let response = request().path(&path).reply(&api).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body(&response)["title"], "How do lifetimes work?");
+    assert_eq!(response.headers()["etag"], "\"0\"");
+
+    let response = request()
+        .method("PUT")
+        .path(&path)
+        .header("authorization", &token)
+        .json(&json!({ "title": "How do lifetimes work in Rust?", "content": "Edited" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK, "{}", text(&response));
+
+    let response = request()
+        .method("PATCH")
+        .path(&path)
+        .header("authorization", &token)
+        .header("if-match", "\"1\"")
+        .json(&json!({ "content": "Patched" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK, "{}", text(&response));
+
+    let response = request().path(&path).reply(&api).await;
+    let question = body(&response);
+    assert_eq!(question["title"], "How do lifetimes work in Rust?");
+    assert_eq!(question["content"], "Patched");
+    assert_eq!(question["version"], 2);
+
+    let response = request()
+        .method("DELETE")
+        .path(&path)
+        .header("authorization", &token)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = request().path(&path).reply(&api).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn answers_a_question_and_pages_through_the_answers() {
+    let api = build_routes(Store::new(), options());
+    let token = token(&api, "answers@example.com").await;
+    let question = add_question(&api, &token, "Which collection should I use?").await;
+    let id = question["id"].as_str().unwrap();
+
+    for n in 0..3 {
+        let response = request()
+            .method("POST")
+            .path("/comments")
+            .header("authorization", &token)
+            .json(&json!({ "content": format!("Answer {}", n), "questionId": id }))
+            .reply(&api)
+            .await;
+        assert!(response.status().is_success(), "{}", text(&response));
+    }
+
+    let path = format!("/questions/{}/comments?limit=2", id);
+    let first = body(&request().path(&path).reply(&api).await);
+    assert_eq!(first["items"].as_array().unwrap().len(), 2);
+    let cursor = first["next_cursor"].as_str().unwrap();
+
+    let path = format!("/questions/{}/comments?limit=2&cursor={}", id, cursor);
+    let second = body(&request().path(&path).reply(&api).await);
+    assert_eq!(second["items"].as_array().unwrap().len(), 1);
+    assert_eq!(second["next_cursor"], Value::Null);
+}
+
+#[tokio::test]
+async fn rejects_malformed_pagination() {
+    let api = build_routes(Store::new(), options());
+
+    for path in [
+        "/questions?limit=abc",
+        "/questions?limit=0",
+        "/questions?offset=-1",
+        "/questions?sort=sideways",
+        "/comments?limit=many",
+        "/comments?limit=0",
+        "/comments?cursor=not-a-cursor",
+    ] {
+        let response = request().path(path).reply(&api).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn error_bodies_carry_the_request_id() {
+    let api = build_routes(Store::new(), options());
+    let response = request().path("/questions/missing").reply(&api).await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let request_id = response.headers()["x-request-id"].to_str().unwrap();
+    assert_eq!(
+        text(&response),
+        format!("Question not found (request id: {})", request_id)
+    );
+}
+
+#[tokio::test]
+async fn maps_rejections_to_status_codes() {
+    let api = build_routes(Store::new(), options());
+    let owner = token(&api, "first@example.com").await;
+    let other = token(&api, "second@example.com").await;
+    let question = add_question(&api, &owner, "Why does the borrow checker complain?").await;
+    let path = format!("/questions/{}", question["id"].as_str().unwrap());
+
+    // Unknown route.
+    let response = request().path("/nowhere").reply(&api).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // No token, and a token that doesn't verify.
+    let response = request()
+        .method("POST")
+        .path("/questions")
+        .json(&json!({ "title": "t", "content": "c" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = request()
+        .method("DELETE")
+        .path(&path)
+        .header("authorization", "Bearer forged")
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Someone else's question.
+    let response = request()
+        .method("DELETE")
+        .path(&path)
+        .header("authorization", &other)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Admin-only listing.
+    let response = request()
+        .path("/questions?include_deleted=true")
+        .header("authorization", &other)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Registering the same email twice.
+    let response = request()
+        .method("POST")
+        .path("/registration")
+        .json(&json!({ "email": "first@example.com", "password": "another-password" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    // A stale version.
+    let response = request()
+        .method("PATCH")
+        .path(&path)
+        .header("authorization", &owner)
+        .header("if-match", "\"7\"")
+        .json(&json!({ "content": "Too late" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+    // A body that isn't a question at all, and one that fails validation.
+    let response = request()
+        .method("POST")
+        .path("/questions")
+        .header("authorization", &owner)
+        .header("content-type", "application/json")
+        .body("{\"title\": 1}")
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let response = request()
+        .method("POST")
+        .path("/questions")
+        .header("authorization", &owner)
+        .json(&json!({ "title": " ", "content": "" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let errors = body(&response)["errors"].clone();
+    assert_eq!(errors[0]["field"], "title");
+    assert_eq!(
+        errors[1],
+        json!({ "field": "content", "message": "must not be empty" })
+    );
+
+    // Offensive content.
+    let response = request()
+        .method("POST")
+        .path("/questions")
+        .header("authorization", &owner)
+        .json(&json!({ "title": "Clean title", "content": "you arsehole" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let response = request()
+        .method("POST")
+        .path(&format!("{}/tags", path))
+        .header("authorization", &owner)
+        .json(&json!(["rust", "bastard"]))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let response = request().path("/tags").reply(&api).await;
+    assert!(!text(&response).contains("bastard"));
+}
+
+#[tokio::test]
+async fn holds_back_duplicates_unless_forced() {
+    let api = build_routes(Store::new(), options());
+    let token = token(&api, "dupes@example.com").await;
+    let original = add_question(&api, &token, "How do I sort a vector?").await;
+
+    let duplicate = json!({ "title": "How to sort a vector", "content": "Same question" });
+    let response = request()
+        .method("POST")
+        .path("/questions")
+        .header("authorization", &token)
+        .json(&duplicate)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(body(&response)["duplicates"][0]["id"], original["id"]);
+
+    let response = request()
+        .method("POST")
+        .path("/questions?force=true")
+        .header("authorization", &token)
+        .json(&duplicate)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn admin_emails_cannot_be_registered() {
+    let api = build_routes(Store::new(), options());
+
+    for email in ["admin@example.com", " Admin@Example.COM "] {
+        let response = request()
+            .method("POST")
+            .path("/registration")
+            .json(&json!({ "email": email, "password": "secret-password" }))
+            .reply(&api)
+            .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", email);
+    }
+
+    let response = request()
+        .method("POST")
+        .path("/login")
+        .json(&json!({ "email": "admin@example.com", "password": "secret-password" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn registration_rejects_malformed_credentials() {
+    let api = build_routes(Store::new(), options());
+
+    for (email, password) in [
+        ("not-an-email", "secret-password"),
+        ("@example.com", "secret-password"),
+        ("short@example.com", "secret"),
+    ] {
+        let response = request()
+            .method("POST")
+            .path("/registration")
+            .json(&json!({ "email": email, "password": password }))
+            .reply(&api)
+            .await;
+        assert_eq!(
+            response.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{}",
+            email
+        );
+    }
+
+    let response = request()
+        .method("POST")
+        .path("/login")
+        .json(&json!({ "email": "short@example.com", "password": "secret" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn profile_emails_are_normalized_like_logins() {
+    let api = build_routes(Store::new(), options());
+    let owner = token(&api, "old@example.com").await;
+    let other = token(&api, "other@example.com").await;
+
+    let response = request()
+        .method("PUT")
+        .path("/accounts/me")
+        .header("authorization", &owner)
+        .json(&json!({ "email": " New@Example.COM " }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK, "{}", text(&response));
+    assert_eq!(body(&response)["email"], "new@example.com");
+
+    for email in ["new@example.com", "NEW@example.com"] {
+        let response = request()
+            .method("POST")
+            .path("/login")
+            .json(&json!({ "email": email, "password": "secret-password" }))
+            .reply(&api)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK, "{}", text(&response));
+    }
+
+    let response = request()
+        .method("PUT")
+        .path("/accounts/me")
+        .header("authorization", &other)
+        .json(&json!({ "email": "NEW@example.com" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn profiles_cannot_take_an_admin_email() {
+    let api = build_routes(Store::new(), options());
+    let user = token(&api, "user@example.com").await;
+
+    let response = request()
+        .method("PUT")
+        .path("/accounts/me")
+        .header("authorization", &user)
+        .json(&json!({ "email": "Admin@Example.com" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = request()
+        .path("/questions?include_deleted=true")
+        .header("authorization", &user)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn limits_clients_that_send_too_many_requests() {
+    let api = build_routes(
+        Store::new(),
+        RouteOptions {
+            rate_limit: 0.001,
+            rate_limit_burst: 1,
+            ..options()
+        },
+    );
+    let client = "127.0.0.1:40000".parse().unwrap();
+
+    let response = request()
+        .path("/health")
+        .remote_addr(client)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = request()
+        .path("/health")
+        .remote_addr(client)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key("retry-after"));
+}